@@ -0,0 +1,110 @@
+use std::fmt::{Display, Formatter};
+
+use rand::Rng;
+
+use crate::engine::search::{SearchInfo, Searcher};
+use crate::engine::training::choose_by_temperature;
+use crate::game::{Checkerboard, PlayerColor};
+use crate::game::moves::{legal_moves, Move};
+
+/**
+Named strengths for the computer player below its full strength. Each level searches to a fixed
+depth and then plays like a person of that level: it picks among the moves that are not much
+worse than the best one, preferring the better ones, and it can overlook its own multi-jumps,
+the longer ones more often.
+ */
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Difficulty {
+  Beginner,
+  Novice,
+  Intermediate,
+  Advanced,
+}
+
+impl Difficulty {
+  pub const ALL: [Difficulty; 4] = [Difficulty::Beginner, Difficulty::Novice, Difficulty::Intermediate, Difficulty::Advanced];
+
+  pub fn depth(self) -> u32 {
+    match self {
+      Difficulty::Beginner => 2,
+      Difficulty::Novice => 3,
+      Difficulty::Intermediate => 4,
+      Difficulty::Advanced => 6,
+    }
+  }
+
+  /// Spread of the move choice in hundredths of a man, as in `choose_by_temperature`.
+  fn temperature(self) -> f32 {
+    match self {
+      Difficulty::Beginner => 80.0,
+      Difficulty::Novice => 45.0,
+      Difficulty::Intermediate => 25.0,
+      Difficulty::Advanced => 10.0,
+    }
+  }
+
+  /// How much worse than the best move, in hundredths of a man, a move may score and still be played.
+  fn max_loss(self) -> i32 {
+    match self {
+      Difficulty::Beginner => 250,
+      Difficulty::Novice => 160,
+      Difficulty::Intermediate => 90,
+      Difficulty::Advanced => 35,
+    }
+  }
+
+  /// Chance of overlooking each jump after the first of a multi-jump.
+  fn miss_chance(self) -> f64 {
+    match self {
+      Difficulty::Beginner => 0.5,
+      Difficulty::Novice => 0.3,
+      Difficulty::Intermediate => 0.15,
+      Difficulty::Advanced => 0.05,
+    }
+  }
+
+  /**
+  Chooses a move of `color` in `board` at this level, with `searcher` limited to the level's
+  depth. Every legal move is searched; multi-jumps the player overlooks are then set aside unless
+  nothing else is left, and the move is drawn from those within the level's loss of the best one.
+  Returns the chosen move's line, or just the first legal move if the search is stopped before any
+  line is complete.
+   */
+  pub fn choose_move(self, board: &Checkerboard, color: PlayerColor, searcher: &mut Searcher,
+                     rng: &mut impl Rng) -> SearchInfo {
+    let moves = legal_moves(board, color);
+    if moves.len() <= 1 {
+      return searcher.search(board, color);
+    }
+    let lines = searcher.search_lines(board, color, moves.len(), |_| {});
+    if lines.is_empty() {
+      return SearchInfo { pv: vec![moves[0].clone()], ..SearchInfo::default() };
+    }
+    let seen: Vec<SearchInfo> = lines.iter()
+        .filter(|line| line.best_move().is_none_or(|mv| !self.overlooks(mv, rng)))
+        .cloned()
+        .collect();
+    let seen = if seen.is_empty() { lines } else { seen };
+    let best = seen.iter().map(|line| line.score).max().unwrap_or_default();
+    let candidates: Vec<SearchInfo> = seen.into_iter().filter(|line| line.score >= best - self.max_loss()).collect();
+    let chosen = choose_by_temperature(&candidates, self.temperature(), rng).cloned();
+    candidates.into_iter().find(|line| line.best_move() == chosen.as_ref()).unwrap_or_default()
+  }
+
+  /// Whether the player fails to see `mv`, which only happens to multi-jumps.
+  fn overlooks(self, mv: &Move, rng: &mut impl Rng) -> bool {
+    let jumps = if mv.is_capture() { mv.path().len() - 1 } else { 0 };
+    (1..jumps).any(|_| rng.gen_bool(self.miss_chance()))
+  }
+}
+
+impl Display for Difficulty {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Difficulty::Beginner => "Beginner",
+      Difficulty::Novice => "Novice",
+      Difficulty::Intermediate => "Intermediate",
+      Difficulty::Advanced => "Advanced",
+    })
+  }
+}
@@ -0,0 +1,40 @@
+use crate::game::{Checkerboard, PlayerColor};
+use crate::game::bitboard::{Bitboard, BOTTOM_EDGE, TOP_EDGE};
+
+pub const MAN_VALUE: i32 = 100;
+pub const KING_VALUE: i32 = 150;
+
+/// Bonus per rank a man has advanced towards promotion.
+const ADVANCE_BONUS: i32 = 3;
+/// Bonus for each man still guarding the home row against enemy promotions.
+const BACK_ROW_BONUS: i32 = 8;
+/// Bonus for each piece on the four central squares.
+const CENTER_BONUS: i32 = 6;
+const CENTER: Bitboard = Bitboard::from_u64(0x0000_0014_2800_0000);
+
+/// Evaluates `board` statically from the point of view of `color`, in hundredths of a man.
+pub fn evaluate(board: &Checkerboard, color: PlayerColor) -> i32 {
+  side_score(board, color) - side_score(board, !color)
+}
+
+fn side_score(board: &Checkerboard, color: PlayerColor) -> i32 {
+  let men = board.men(color);
+  let kings = board.kings(color);
+  let mut score = men.count() as i32 * MAN_VALUE + kings.count() as i32 * KING_VALUE;
+
+  let home_row = match color {
+    PlayerColor::White => BOTTOM_EDGE,
+    PlayerColor::Black => TOP_EDGE,
+  };
+  for man in men.squares() {
+    let rank = man.index() >> 3;
+    let advanced = match color {
+      PlayerColor::White => rank,
+      PlayerColor::Black => 7 - rank,
+    };
+    score += advanced * ADVANCE_BONUS;
+  }
+  score += (men & home_row).count() as i32 * BACK_ROW_BONUS;
+  score += ((men | kings) & CENTER).count() as i32 * CENTER_BONUS;
+  score
+}
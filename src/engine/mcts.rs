@@ -0,0 +1,277 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::engine::eval::evaluate;
+use crate::engine::search::{MATE, MATE_BOUND, SearchInfo};
+use crate::game::{Checkerboard, PlayerColor};
+use crate::game::moves::{color_captures, legal_moves, Move};
+
+/// Weight of the exploration term in UCT; larger values try rarely visited moves more often.
+const EXPLORATION: f64 = 1.4;
+/// Playouts still going after this many plies are scored by the evaluation.
+const MAX_PLAYOUT: u32 = 100;
+/// Evaluation, in hundredths of a man, at which a side's chances are about 73% (logistic curve).
+const EVAL_SCALE: f64 = 200.0;
+
+/// How moves are chosen while playing a position out to the end.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Playout {
+  /// Uniformly at random among the legal moves.
+  Random,
+  /// At random among the moves that crown a man, then those that leave nothing to capture.
+  Heuristic,
+}
+
+/// When to stop searching. With no limits set the search runs until stopped.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MctsLimits {
+  pub iterations: Option<u64>,
+  pub time: Option<Duration>,
+}
+
+struct Node {
+  board: Checkerboard,
+  /// The side to move.
+  color: PlayerColor,
+  /// The move that led here from the parent; `None` for the root.
+  mv: Option<Move>,
+  children: Vec<usize>,
+  /// Legal moves not yet expanded into children, in random order.
+  untried: Vec<Move>,
+  visits: u32,
+  /// Sum of the playout results for the side that moved into this node: 1 a win, 0 a loss.
+  wins: f64,
+}
+
+/**
+Monte Carlo tree search with UCT selection. The tree is kept between searches, so when the next
+position searched follows from the last one by a move or two the matching subtree and its
+statistics are reused.
+ */
+pub struct Mcts {
+  limits: MctsLimits,
+  playout: Playout,
+  /// The tree, with the root first.
+  nodes: Vec<Node>,
+  rng: StdRng,
+  stop: Arc<AtomicBool>,
+}
+
+impl Mcts {
+  pub fn new(limits: MctsLimits, playout: Playout) -> Mcts {
+    Mcts {
+      limits,
+      playout,
+      nodes: Vec::new(),
+      rng: StdRng::from_entropy(),
+      stop: Arc::new(AtomicBool::new(false)),
+    }
+  }
+
+  /// Makes the playouts repeat from run to run.
+  pub fn with_seed(mut self, seed: u64) -> Mcts {
+    self.rng = StdRng::seed_from_u64(seed);
+    self
+  }
+
+  pub fn set_limits(&mut self, limits: MctsLimits) {
+    self.limits = limits;
+  }
+
+  /// Uses `stop` as the cancellation flag; setting it ends the search after the current iteration.
+  pub fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+    self.stop = stop;
+  }
+
+  /// Number of nodes in the tree, which include those kept from earlier searches.
+  pub fn tree_size(&self) -> usize {
+    self.nodes.len()
+  }
+
+  /**
+  Searches `board` for `color` until a limit is reached or the search is stopped. The best move is
+  the most visited one and the principal variation follows the most visited children; the score
+  is the best move's winning rate converted to hundredths of a man, and `nodes` counts the
+  iterations of this search.
+   */
+  pub fn search(&mut self, board: &Checkerboard, color: PlayerColor) -> SearchInfo {
+    let start = Instant::now();
+    self.set_root(board, color);
+    if self.nodes[0].untried.is_empty() && self.nodes[0].children.is_empty() {
+      return SearchInfo { score: -MATE, ..SearchInfo::default() };
+    }
+
+    let mut iterations = 0;
+    loop {
+      if self.stop.load(Ordering::Relaxed)
+          || self.limits.iterations.is_some_and(|limit| iterations >= limit)
+          || (iterations & 63 == 0 && self.limits.time.is_some_and(|time| start.elapsed() >= time)) {
+        break;
+      }
+      self.iterate();
+      iterations += 1;
+    }
+
+    let most_visited = |node: &Node| node.children.iter().copied().max_by_key(|&child| self.nodes[child].visits);
+    let (score, pv) = match most_visited(&self.nodes[0]) {
+      Some(best) => {
+        let mut pv = Vec::new();
+        let mut node = Some(best);
+        while let Some(next) = node {
+          pv.extend(self.nodes[next].mv.clone());
+          node = most_visited(&self.nodes[next]);
+        }
+        let rate = (self.nodes[best].wins / self.nodes[best].visits as f64).clamp(0.001, 0.999);
+        (((EVAL_SCALE * (rate / (1.0 - rate)).ln()) as i32).clamp(-MATE_BOUND, MATE_BOUND), pv)
+      }
+      // stopped before the first iteration
+      None => (0, self.nodes[0].untried.first().cloned().into_iter().collect()),
+    };
+    SearchInfo { depth: pv.len() as u32, score, pv, nodes: iterations, elapsed: start.elapsed() }
+  }
+
+  /// Makes the node for `board` the root, keeping its subtree if it is within two plies of the old root.
+  fn set_root(&mut self, board: &Checkerboard, color: PlayerColor) {
+    let matches = |node: &Node| node.board == *board && node.color == color;
+    let found = match self.nodes.first() {
+      Some(root) if matches(root) => return,
+      Some(root) => root.children.iter()
+          .flat_map(|&child| std::iter::once(child).chain(self.nodes[child].children.iter().copied()))
+          .find(|&node| matches(&self.nodes[node])),
+      None => None,
+    };
+    let mut old = std::mem::take(&mut self.nodes);
+    match found {
+      Some(root) => self.keep_subtree(&mut old, root),
+      None => {
+        let node = self.new_node(*board, color, None);
+        self.nodes.push(node);
+      }
+    }
+  }
+
+  /// Moves the subtree of `old` below `node` into the tree, renumbering its nodes.
+  fn keep_subtree(&mut self, old: &mut [Node], node: usize) {
+    let index = self.nodes.len();
+    let placeholder = Node {
+      board: old[node].board,
+      color: old[node].color,
+      mv: None,
+      children: Vec::new(),
+      untried: Vec::new(),
+      visits: 0,
+      wins: 0.0,
+    };
+    let mut kept = std::mem::replace(&mut old[node], placeholder);
+    if index == 0 {
+      kept.mv = None;
+    }
+    let children = std::mem::take(&mut kept.children);
+    self.nodes.push(kept);
+    for child in children {
+      let child_index = self.nodes.len();
+      self.keep_subtree(old, child);
+      self.nodes[index].children.push(child_index);
+    }
+  }
+
+  fn new_node(&mut self, board: Checkerboard, color: PlayerColor, mv: Option<Move>) -> Node {
+    let mut untried = legal_moves(&board, color);
+    untried.shuffle(&mut self.rng);
+    Node { board, color, mv, children: Vec::new(), untried, visits: 0, wins: 0.0 }
+  }
+
+  /// Selects down the tree by UCT, expands one move, plays the new position out and backs up the result.
+  fn iterate(&mut self) {
+    let mut path = vec![0];
+    let mut node = 0;
+    while self.nodes[node].untried.is_empty() && !self.nodes[node].children.is_empty() {
+      node = self.select(node);
+      path.push(node);
+    }
+    if let Some(mv) = self.nodes[node].untried.pop() {
+      let mut board = self.nodes[node].board;
+      let color = self.nodes[node].color;
+      board.apply(color, &mv);
+      let child = self.new_node(board, !color, Some(mv));
+      self.nodes.push(child);
+      let child = self.nodes.len() - 1;
+      self.nodes[node].children.push(child);
+      path.push(child);
+      node = child;
+    }
+
+    let leaf_color = self.nodes[node].color;
+    let result = self.play_out(self.nodes[node].board, leaf_color);
+    for &node in &path {
+      let node = &mut self.nodes[node];
+      node.visits += 1;
+      node.wins += if node.color == leaf_color { 1.0 - result } else { result };
+    }
+  }
+
+  fn select(&self, node: usize) -> usize {
+    let parent_log = (self.nodes[node].visits as f64).ln();
+    let uct = |child: usize| {
+      let child = &self.nodes[child];
+      let visits = child.visits as f64;
+      child.wins / visits + EXPLORATION * (parent_log / visits).sqrt()
+    };
+    self.nodes[node].children.iter().copied()
+        .max_by(|&a, &b| uct(a).total_cmp(&uct(b)))
+        .unwrap()
+  }
+
+  /// Plays `board` out from `color`'s move and returns `color`'s result between 0 and 1.
+  fn play_out(&mut self, mut board: Checkerboard, color: PlayerColor) -> f64 {
+    let mut mover = color;
+    for _ in 0..MAX_PLAYOUT {
+      let moves = legal_moves(&board, mover);
+      let mv = match self.playout {
+        Playout::Random => moves.choose(&mut self.rng),
+        Playout::Heuristic => self.heuristic_move(&board, mover, &moves),
+      };
+      match mv {
+        Some(mv) => {
+          board.apply(mover, mv);
+          mover = !mover;
+        }
+        None => return if mover == color { 0.0 } else { 1.0 },
+      }
+    }
+    1.0 / (1.0 + (-evaluate(&board, color) as f64 / EVAL_SCALE).exp())
+  }
+
+  fn heuristic_move<'a>(&mut self, board: &Checkerboard, color: PlayerColor, moves: &'a [Move]) -> Option<&'a Move> {
+    if moves.len() <= 1 {
+      return moves.first();
+    }
+    let kings = board.kings(color).count();
+    let mut best = Vec::new();
+    let mut best_rank = 0;
+    for mv in moves {
+      let mut next = *board;
+      next.apply(color, mv);
+      let rank = if next.kings(color).count() > kings {
+        2
+      } else if color_captures(&next, !color).is_empty() {
+        1
+      } else {
+        0
+      };
+      if rank > best_rank {
+        best.clear();
+        best_rank = rank;
+      }
+      if rank == best_rank {
+        best.push(mv);
+      }
+    }
+    best.get(self.rng.gen_range(0..best.len())).copied()
+  }
+}
@@ -0,0 +1,9 @@
+pub mod difficulty;
+pub mod eval;
+pub mod mcts;
+pub mod network;
+pub mod review;
+pub mod search;
+pub mod time;
+pub mod training;
+pub mod tt;
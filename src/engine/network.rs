@@ -0,0 +1,180 @@
+use std::fmt::{Debug, Formatter};
+use std::fs;
+use std::path::Path;
+
+use eyre::{eyre, Result, WrapErr};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::game::{Checkerboard, PlayerColor};
+use crate::game::bitboard::Bitboard;
+
+/// One input per piece kind (as numbered by `Checkerboard::index`) and dark square.
+pub const INPUTS: usize = 4 * 32;
+/// Size of the hidden layer.
+pub const HIDDEN: usize = 32;
+/// Start of a weights file, followed by the format version.
+const MAGIC: &[u8; 4] = b"CKNN";
+const VERSION: u32 = 1;
+
+/**
+A small evaluation network: the piece bitboards feed a hidden layer with ReLU activation, which
+feeds a single output, the score in hundredths of a man from White's point of view.
+
+Weights files are little-endian: the magic `CKNN`, the version and the hidden size as `u32`, then
+the `f32` input weights input by input, the hidden biases, the output weights and the output bias.
+ */
+#[derive(Clone, PartialEq)]
+pub struct Network {
+  /// `HIDDEN` weights per input, input by input.
+  pub(crate) input_weights: Vec<f32>,
+  pub(crate) hidden_bias: Vec<f32>,
+  pub(crate) output_weights: Vec<f32>,
+  pub(crate) output_bias: f32,
+}
+
+impl Network {
+  /// A network with small random weights, to be trained.
+  pub fn random(seed: u64) -> Network {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut weights = |count: usize, scale: f32| (0..count).map(|_| rng.gen_range(-scale..scale)).collect::<Vec<f32>>();
+    Network {
+      input_weights: weights(INPUTS * HIDDEN, 0.5),
+      hidden_bias: weights(HIDDEN, 0.1),
+      output_weights: weights(HIDDEN, 10.0),
+      output_bias: 0.0,
+    }
+  }
+
+  pub fn load(path: &Path) -> Result<Network> {
+    let bytes = fs::read(path).wrap_err_with(|| format!("could not read {}", path.display()))?;
+    Network::from_bytes(&bytes).wrap_err_with(|| format!("invalid weights file {}", path.display()))
+  }
+
+  pub fn save(&self, path: &Path) -> Result<()> {
+    fs::write(path, self.to_bytes()).wrap_err_with(|| format!("could not write {}", path.display()))
+  }
+
+  pub fn from_bytes(bytes: &[u8]) -> Result<Network> {
+    if bytes.len() < 12 || &bytes[..4] != MAGIC {
+      return Err(eyre!("not a network weights file"));
+    }
+    let header = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+    if header(4) != VERSION {
+      return Err(eyre!("unsupported weights version {}", header(4)));
+    }
+    if header(8) as usize != HIDDEN {
+      return Err(eyre!("hidden layer of {} units, expected {}", header(8), HIDDEN));
+    }
+    let floats: Vec<f32> = bytes[12..].chunks(4).map(|chunk| match chunk.try_into() {
+      Ok(chunk) => Ok(f32::from_le_bytes(chunk)),
+      Err(_) => Err(eyre!("truncated weights")),
+    }).collect::<Result<_>>()?;
+    let expected = INPUTS * HIDDEN + 2 * HIDDEN + 1;
+    if floats.len() != expected {
+      return Err(eyre!("{} weights, expected {}", floats.len(), expected));
+    }
+    let (input_weights, rest) = floats.split_at(INPUTS * HIDDEN);
+    let (hidden_bias, rest) = rest.split_at(HIDDEN);
+    let (output_weights, rest) = rest.split_at(HIDDEN);
+    Ok(Network {
+      input_weights: input_weights.to_vec(),
+      hidden_bias: hidden_bias.to_vec(),
+      output_weights: output_weights.to_vec(),
+      output_bias: rest[0],
+    })
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(VERSION.to_le_bytes());
+    bytes.extend((HIDDEN as u32).to_le_bytes());
+    let floats = self.input_weights.iter().chain(&self.hidden_bias).chain(&self.output_weights).chain([&self.output_bias]);
+    for float in floats {
+      bytes.extend(float.to_le_bytes());
+    }
+    bytes
+  }
+
+  /// Evaluates `board` from `color`'s point of view, like `eval::evaluate`.
+  pub fn evaluate(&self, board: &Checkerboard, color: PlayerColor) -> i32 {
+    self.score(&Accumulator::new(self, board), color)
+  }
+
+  /// The score of the position summed up in `accumulator`, from `color`'s point of view.
+  pub fn score(&self, accumulator: &Accumulator, color: PlayerColor) -> i32 {
+    let score = self.output(&accumulator.values) as i32;
+    if color == PlayerColor::White { score } else { -score }
+  }
+
+  pub(crate) fn output(&self, hidden: &[f32]) -> f32 {
+    let sum: f32 = hidden.iter().zip(&self.output_weights).map(|(value, weight)| value.max(0.0) * weight).sum();
+    self.output_bias + sum
+  }
+
+  fn add_input(&self, values: &mut [f32], input: usize, sign: f32) {
+    let weights = &self.input_weights[input * HIDDEN..(input + 1) * HIDDEN];
+    for (value, weight) in values.iter_mut().zip(weights) {
+      *value += sign * weight;
+    }
+  }
+}
+
+impl Debug for Network {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "Network({}x{}x1)", INPUTS, HIDDEN)
+  }
+}
+
+/// The inputs that are on for the pieces of `board`.
+pub(crate) fn active_inputs(board: &Checkerboard) -> impl Iterator<Item = usize> + '_ {
+  piece_sets(board).into_iter().enumerate()
+      .flat_map(|(kind, pieces)| pieces.squares().map(move |square| input(kind, square)))
+}
+
+fn piece_sets(board: &Checkerboard) -> [Bitboard; 4] {
+  let mut sets = [Bitboard::new(); 4];
+  for color in [PlayerColor::White, PlayerColor::Black] {
+    sets[Checkerboard::index(color, false)] = board.men(color);
+    sets[Checkerboard::index(color, true)] = board.kings(color);
+  }
+  sets
+}
+
+/// Dark squares have even indices on even ranks and odd ones on odd ranks, so halving numbers them 0 to 31.
+fn input(kind: usize, square: Bitboard) -> usize {
+  kind * 32 + (square.index() as usize >> 1)
+}
+
+/**
+The hidden layer's inputs for one position: the biases plus the weights of every piece. After a
+move only the weights of the pieces that moved, were captured or were crowned change, so it is
+updated from the squares that changed instead of being summed again.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accumulator {
+  pub(crate) values: [f32; HIDDEN],
+}
+
+impl Accumulator {
+  pub fn new(network: &Network, board: &Checkerboard) -> Accumulator {
+    let mut values = [0.0; HIDDEN];
+    values.copy_from_slice(&network.hidden_bias);
+    for input in active_inputs(board) {
+      network.add_input(&mut values, input, 1.0);
+    }
+    Accumulator { values }
+  }
+
+  /// Updates the accumulator of `before` to that of `after`, usually the position after a move.
+  pub fn update(&mut self, network: &Network, before: &Checkerboard, after: &Checkerboard) {
+    for (kind, (old, new)) in piece_sets(before).into_iter().zip(piece_sets(after)).enumerate() {
+      for square in (old & !new).squares() {
+        network.add_input(&mut self.values, input(kind, square), -1.0);
+      }
+      for square in (new & !old).squares() {
+        network.add_input(&mut self.values, input(kind, square), 1.0);
+      }
+    }
+  }
+}
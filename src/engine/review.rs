@@ -0,0 +1,192 @@
+use std::fmt::{Display, Formatter};
+
+use crate::engine::search::{format_score, Searcher};
+use crate::game::{Checkerboard, PlayerColor};
+use crate::game::moves::Move;
+use crate::game::pdn::{Annotation, PdnGame};
+
+/// Score drops, in hundredths of a man, from which a move counts as an inaccuracy, a mistake or a
+/// blunder.
+pub const INACCURACY: i32 = 40;
+pub const MISTAKE: i32 = 90;
+pub const BLUNDER: i32 = 200;
+/// Scores are capped at this size before comparing them, so choosing a slower win or a faster
+/// loss of an already decided game is not punished.
+const SCORE_CAP: i32 = 1000;
+
+/// How a played move compares to the best move found.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum MoveQuality {
+  Good,
+  Inaccuracy,
+  Mistake,
+  Blunder,
+}
+
+impl MoveQuality {
+  pub fn from_loss(loss: i32) -> MoveQuality {
+    match loss {
+      loss if loss >= BLUNDER => MoveQuality::Blunder,
+      loss if loss >= MISTAKE => MoveQuality::Mistake,
+      loss if loss >= INACCURACY => MoveQuality::Inaccuracy,
+      _ => MoveQuality::Good,
+    }
+  }
+
+  /// The PDN numeric annotation glyph: `$6` (?!), `$2` (?) or `$4` (??).
+  pub fn nag(self) -> Option<u8> {
+    match self {
+      MoveQuality::Good => None,
+      MoveQuality::Inaccuracy => Some(6),
+      MoveQuality::Mistake => Some(2),
+      MoveQuality::Blunder => Some(4),
+    }
+  }
+
+  /// The suffix that marks the move in a move list.
+  pub fn symbol(self) -> &'static str {
+    match self {
+      MoveQuality::Good => "",
+      MoveQuality::Inaccuracy => "?!",
+      MoveQuality::Mistake => "?",
+      MoveQuality::Blunder => "??",
+    }
+  }
+}
+
+impl Display for MoveQuality {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      MoveQuality::Good => f.write_str("Good move"),
+      MoveQuality::Inaccuracy => f.write_str("Inaccuracy"),
+      MoveQuality::Mistake => f.write_str("Mistake"),
+      MoveQuality::Blunder => f.write_str("Blunder"),
+    }
+  }
+}
+
+/// A played move next to the best move in the same position. Scores are from the mover's side.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MoveReview {
+  pub color: PlayerColor,
+  pub played: Move,
+  pub best: Move,
+  pub best_score: i32,
+  pub played_score: i32,
+}
+
+impl MoveReview {
+  /// How much worse the played move scored than the best one; 0 if it was the best move.
+  pub fn loss(&self) -> i32 {
+    if self.played == self.best {
+      return 0;
+    }
+    (self.best_score.clamp(-SCORE_CAP, SCORE_CAP) - self.played_score.clamp(-SCORE_CAP, SCORE_CAP)).max(0)
+  }
+
+  pub fn quality(&self) -> MoveQuality {
+    MoveQuality::from_loss(self.loss())
+  }
+
+  /// 100 minus the percentage points of winning chances the move gave away.
+  pub fn accuracy(&self) -> f64 {
+    let best = self.best_score.clamp(-SCORE_CAP, SCORE_CAP);
+    100.0 - (win_chance(best) - win_chance(best - self.loss()))
+  }
+}
+
+/// The mover's chances of winning in percent, estimated from a capped score with a logistic curve.
+fn win_chance(score: i32) -> f64 {
+  100.0 / (1.0 + (-score as f64 / 200.0).exp())
+}
+
+/// The reviews of every move of a game, in order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GameReview {
+  pub moves: Vec<MoveReview>,
+}
+
+impl GameReview {
+  /// The average accuracy of `color`'s moves, if they made any.
+  pub fn accuracy(&self, color: PlayerColor) -> Option<f64> {
+    let accuracies: Vec<f64> = self.moves.iter().filter(|mv| mv.color == color).map(MoveReview::accuracy).collect();
+    if accuracies.is_empty() {
+      None
+    } else {
+      Some(accuracies.iter().sum::<f64>() / accuracies.len() as f64)
+    }
+  }
+
+  pub fn count(&self, color: PlayerColor, quality: MoveQuality) -> usize {
+    self.moves.iter().filter(|mv| mv.color == color && mv.quality() == quality).count()
+  }
+
+  /// One line per player, e.g. `White: 91.5% accuracy, 1 inaccuracy, 0 mistakes, 1 blunder`.
+  pub fn summary(&self, color: PlayerColor) -> String {
+    let count = |quality: MoveQuality, one: &str, many: &str| {
+      let count = self.count(color, quality);
+      format!("{} {}", count, if count == 1 { one } else { many })
+    };
+    let accuracy = match self.accuracy(color) {
+      Some(accuracy) => format!("{:.1}% accuracy", accuracy),
+      None => String::from("no moves"),
+    };
+    format!("{}: {}, {}, {}, {}", color, accuracy,
+            count(MoveQuality::Inaccuracy, "inaccuracy", "inaccuracies"),
+            count(MoveQuality::Mistake, "mistake", "mistakes"),
+            count(MoveQuality::Blunder, "blunder", "blunders"))
+  }
+
+  /**
+  Adds the review to `pdn`, whose moves must be the reviewed ones: each inaccuracy, mistake and
+  blunder gets its glyph and a comment naming the better move, and the players' accuracy goes into
+  `WhiteAccuracy` and `BlackAccuracy` tags.
+   */
+  pub fn annotate(&self, pdn: &mut PdnGame) {
+    pdn.annotations = self.moves.iter().map(|mv| {
+      let quality = mv.quality();
+      Annotation {
+        nag: quality.nag(),
+        comment: (quality != MoveQuality::Good).then(|| {
+          format!("{}, {}. Best was {} ({})", quality, format_score(mv.played_score), mv.best, format_score(mv.best_score))
+        }),
+      }
+    }).collect();
+    pdn.set_tag("Annotator", "checkers review");
+    for color in [PlayerColor::White, PlayerColor::Black] {
+      if let Some(accuracy) = self.accuracy(color) {
+        pdn.set_tag(&format!("{}Accuracy", color), format!("{:.1}", accuracy));
+      }
+    }
+  }
+}
+
+/**
+Searches every position of a game with `searcher` and compares each played move with the best
+move found. `moves` must be legal moves written out in full, such as a game's history. A played
+move is scored by searching the position after it, so every position is searched only once.
+ */
+pub fn review_game(start: (Checkerboard, PlayerColor), moves: &[Move], searcher: &mut Searcher) -> GameReview {
+  let (mut board, mut color) = start;
+  let mut searches = vec![searcher.search(&board, color)];
+  for mv in moves {
+    board.apply(color, mv);
+    color = !color;
+    searches.push(searcher.search(&board, color));
+  }
+
+  let mut color = start.1;
+  let mut review = GameReview::default();
+  for (i, played) in moves.iter().enumerate() {
+    let before = &searches[i];
+    review.moves.push(MoveReview {
+      color,
+      played: played.clone(),
+      best: before.best_move().cloned().unwrap_or_else(|| played.clone()),
+      best_score: before.score,
+      played_score: -searches[i + 1].score,
+    });
+    color = !color;
+  }
+  review
+}
@@ -0,0 +1,489 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::engine::eval::evaluate;
+use crate::engine::network::{Accumulator, Network};
+use crate::engine::time::{ClockTime, TimeManager};
+use crate::engine::tt::{Bound, position_key, TableEntry, TranspositionTable};
+use crate::game::{Checkerboard, PlayerColor};
+use crate::game::moves::{color_captures, color_moves, legal_moves, Move};
+
+/// Score of a won position. Wins found deeper in the tree score `MATE - ply`.
+pub const MATE: i32 = 100_000;
+/// Scores beyond this magnitude are forced wins or losses.
+pub const MATE_BOUND: i32 = MATE - 1000;
+const MAX_DEPTH: u32 = 64;
+/// Plies of captures searched past the nominal depth by default.
+pub const QUIESCENCE_DEPTH: u32 = 16;
+
+/// When to stop searching. Unset limits are unbounded; with no limits at all the search runs to
+/// `MAX_DEPTH` or until stopped.
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+  pub depth: Option<u32>,
+  pub time: Option<Duration>,
+  pub nodes: Option<u64>,
+  /// Lets a `TimeManager` decide how long to search from the clock of the side to move.
+  pub clock: Option<ClockTime>,
+}
+
+impl SearchLimits {
+  pub fn depth(depth: u32) -> SearchLimits {
+    SearchLimits { depth: Some(depth), ..SearchLimits::default() }
+  }
+
+  pub fn time(time: Duration) -> SearchLimits {
+    SearchLimits { time: Some(time), ..SearchLimits::default() }
+  }
+}
+
+/// The outcome of a completed search iteration.
+#[derive(Clone, Debug, Default)]
+pub struct SearchInfo {
+  pub depth: u32,
+  pub score: i32,
+  pub pv: Vec<Move>,
+  pub nodes: u64,
+  pub elapsed: Duration,
+}
+
+impl SearchInfo {
+  pub fn best_move(&self) -> Option<&Move> {
+    self.pv.first()
+  }
+}
+
+/**
+Iterative-deepening alpha-beta search with a transposition table, which can be stopped from
+another thread. Pending captures are played out past the nominal depth before a position is
+evaluated. With more than one thread it runs Lazy SMP: helper threads search the same
+position on their own, sharing only the table, and the main thread's result is used. A single
+thread, the default, gives the same result every time for the same searches.
+ */
+pub struct Searcher {
+  limits: SearchLimits,
+  stop: Arc<AtomicBool>,
+  start: Instant,
+  nodes: u64,
+  aborted: bool,
+  table: Arc<TranspositionTable>,
+  threads: usize,
+  /// Set on helper threads, whose iterations start one ply deeper on odd-numbered threads so the
+  /// threads do not all search the same tree in step.
+  helper: Option<usize>,
+  /// Evaluates positions instead of `eval::evaluate` if set.
+  network: Option<Arc<Network>>,
+  /// The network's accumulator for the position at each ply of the current line.
+  accumulators: Vec<Accumulator>,
+  /// Plans the current search's time if the limits have a clock.
+  time_manager: Option<TimeManager>,
+  /// Plies of captures searched past the nominal depth before a position is evaluated anyway.
+  quiescence: u32,
+}
+
+impl Searcher {
+  pub fn new(limits: SearchLimits) -> Searcher {
+    Searcher {
+      limits,
+      stop: Arc::new(AtomicBool::new(false)),
+      start: Instant::now(),
+      nodes: 0,
+      aborted: false,
+      table: Arc::new(TranspositionTable::default()),
+      threads: 1,
+      helper: None,
+      network: None,
+      accumulators: Vec::new(),
+      time_manager: None,
+      quiescence: QUIESCENCE_DEPTH,
+    }
+  }
+
+  /// Evaluates positions with `network` instead of the handwritten evaluation.
+  pub fn with_network(mut self, network: Option<Arc<Network>>) -> Searcher {
+    self.network = network;
+    self
+  }
+
+  /// Plays out captures for at most `plies` plies past the nominal depth; 0 evaluates there directly.
+  pub fn with_quiescence(mut self, plies: u32) -> Searcher {
+    self.quiescence = plies;
+    self
+  }
+
+  /// Searches with `threads` threads in all, at least one.
+  pub fn with_threads(mut self, threads: usize) -> Searcher {
+    self.threads = threads.max(1);
+    self
+  }
+
+  pub fn set_limits(&mut self, limits: SearchLimits) {
+    self.limits = limits;
+  }
+
+  /// Forgets the positions stored by earlier searches.
+  pub fn clear_table(&self) {
+    self.table.clear();
+  }
+
+  /// Uses `stop` as the cancellation flag; setting it ends the search at the next check.
+  pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> Searcher {
+    self.stop = stop;
+    self
+  }
+
+  pub fn stop_flag(&self) -> Arc<AtomicBool> {
+    self.stop.clone()
+  }
+
+  pub fn search(&mut self, board: &Checkerboard, color: PlayerColor) -> SearchInfo {
+    self.search_with(board, color, |_| {})
+  }
+
+  /**
+  Searches `board` for `color`, calling `on_info` after every completed iteration. Returns the
+  result of the deepest completed iteration; if the search is stopped before the first iteration
+  finishes, the first legal move is returned so callers always have something to play.
+   */
+  pub fn search_with(&mut self, board: &Checkerboard, color: PlayerColor, on_info: impl FnMut(&SearchInfo)) -> SearchInfo {
+    self.with_helpers(board, color, |searcher| searcher.iterate(board, color, on_info))
+  }
+
+  fn iterate(&mut self, board: &Checkerboard, color: PlayerColor, mut on_info: impl FnMut(&SearchInfo)) -> SearchInfo {
+
+    let mut best = SearchInfo {
+      pv: legal_moves(board, color).into_iter().take(1).collect(),
+      ..SearchInfo::default()
+    };
+    if best.pv.is_empty() {
+      best.score = -MATE;
+      return best;
+    }
+
+    let mut max_depth = self.limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+    // on the clock a forced move is played at once
+    if self.time_manager.is_some() && legal_moves(board, color).len() == 1 {
+      max_depth = 1;
+    }
+    let first_depth = match self.helper {
+      Some(helper) => (1 + helper as u32 % 2).min(max_depth),
+      None => 1,
+    };
+    for depth in first_depth..=max_depth {
+      let mut pv = Vec::new();
+      let score = self.negamax(board, color, depth, 0, -MATE - 1, MATE + 1, &best.pv, &mut pv);
+      if self.aborted {
+        break;
+      }
+      best = SearchInfo { depth, score, pv, nodes: self.nodes, elapsed: self.start.elapsed() };
+      on_info(&best);
+      if score.abs() > MATE_BOUND {
+        break;
+      }
+      let elapsed = self.start.elapsed();
+      if self.time_manager.as_mut().is_some_and(|manager| manager.iteration_done(&best, elapsed)) {
+        break;
+      }
+    }
+    best.nodes = self.nodes;
+    best.elapsed = self.start.elapsed();
+    best
+  }
+
+  /**
+  Searches `board` for the best `count` moves of `color`, each with its own score and principal
+  variation, calling `on_info` with the lines, best first, after every completed iteration. Each
+  root move is searched with a window that only has to beat the weakest line kept so far, so the
+  cost grows with `count`. Returns the lines of the deepest completed iteration; empty if there
+  are no legal moves.
+   */
+  pub fn search_lines(&mut self, board: &Checkerboard, color: PlayerColor, count: usize,
+                      mut on_info: impl FnMut(&[SearchInfo])) -> Vec<SearchInfo> {
+    self.with_helpers(board, color, |searcher| searcher.iterate_lines(board, color, count, &mut on_info))
+  }
+
+  fn iterate_lines(&mut self, board: &Checkerboard, color: PlayerColor, count: usize,
+                   mut on_info: impl FnMut(&[SearchInfo])) -> Vec<SearchInfo> {
+    let mut moves = legal_moves(board, color);
+    let count = count.clamp(1, moves.len().max(1));
+    let mut best: Vec<SearchInfo> = Vec::new();
+    let max_depth = self.limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+    for depth in 1..=max_depth {
+      if moves.is_empty() {
+        break;
+      }
+      // search the previous iteration's lines first, in their order
+      for (i, line) in best.iter().enumerate() {
+        if let Some(j) = moves.iter().position(|mv| Some(mv) == line.best_move()) {
+          moves[i..].swap(0, j - i);
+        }
+      }
+      let mut lines: Vec<SearchInfo> = Vec::new();
+      for mv in &moves {
+        let alpha = if lines.len() < count { -MATE - 1 } else { lines[count - 1].score };
+        let prev_pv = best.iter().find(|line| line.best_move() == Some(mv)).map_or(&[][..], |line| &line.pv[1..]);
+        let mut next = *board;
+        next.apply(color, mv);
+        self.enter_child(0, board, &next);
+        let mut line = Vec::new();
+        let score = -self.negamax(&next, !color, depth - 1, 1, -MATE - 1, -alpha, prev_pv, &mut line);
+        if self.aborted {
+          break;
+        }
+        if score > alpha {
+          let mut pv = vec![mv.clone()];
+          pv.extend(line);
+          let at = lines.iter().position(|other| other.score < score).unwrap_or(lines.len());
+          lines.insert(at, SearchInfo { depth, score, pv, nodes: 0, elapsed: Duration::ZERO });
+          lines.truncate(count);
+        }
+      }
+      if self.aborted {
+        break;
+      }
+      for line in &mut lines {
+        line.nodes = self.nodes;
+        line.elapsed = self.start.elapsed();
+      }
+      best = lines;
+      on_info(&best);
+    }
+    best
+  }
+
+  /**
+  Runs `search` on this thread while helper threads, if any, search `board` until it returns.
+  Node counts and limits are the main thread's.
+   */
+  fn with_helpers<R>(&mut self, board: &Checkerboard, color: PlayerColor, search: impl FnOnce(&mut Searcher) -> R) -> R {
+    self.start = Instant::now();
+    self.nodes = 0;
+    self.aborted = false;
+    self.time_manager = self.limits.clock.as_ref().map(TimeManager::new);
+    self.set_root(board);
+    if self.threads == 1 {
+      return search(self);
+    }
+
+    let helpers_stop = Arc::new(AtomicBool::new(false));
+    thread::scope(|scope| {
+      for helper in 1..self.threads {
+        let mut searcher = Searcher {
+          limits: SearchLimits { depth: self.limits.depth.map(|depth| depth + 1), ..SearchLimits::default() },
+          stop: helpers_stop.clone(),
+          start: Instant::now(),
+          nodes: 0,
+          aborted: false,
+          table: self.table.clone(),
+          threads: 1,
+          helper: Some(helper),
+          network: self.network.clone(),
+          accumulators: Vec::new(),
+          time_manager: None,
+          quiescence: self.quiescence,
+        };
+        searcher.set_root(board);
+        scope.spawn(move || searcher.iterate(board, color, |_| {}));
+      }
+      let result = search(self);
+      helpers_stop.store(true, Ordering::Relaxed);
+      result
+    })
+  }
+
+  fn set_root(&mut self, board: &Checkerboard) {
+    self.accumulators.clear();
+    if let Some(network) = &self.network {
+      self.accumulators.push(Accumulator::new(network, board));
+    }
+  }
+
+  /// Derives the accumulator for `next`, reached from `board` at `ply`, from that of `board`.
+  fn enter_child(&mut self, ply: u32, board: &Checkerboard, next: &Checkerboard) {
+    let network = match &self.network {
+      Some(network) => network,
+      None => return,
+    };
+    let ply = ply as usize;
+    if self.accumulators.len() <= ply + 1 {
+      let last = self.accumulators[ply].clone();
+      self.accumulators.resize(ply + 2, last);
+    }
+    let (parents, children) = self.accumulators.split_at_mut(ply + 1);
+    children[0].clone_from(&parents[ply]);
+    children[0].update(network, board, next);
+  }
+
+  fn evaluate(&self, board: &Checkerboard, color: PlayerColor, ply: u32) -> i32 {
+    match &self.network {
+      Some(network) => network.score(&self.accumulators[ply as usize], color),
+      None => evaluate(board, color),
+    }
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  fn negamax(&mut self, board: &Checkerboard, color: PlayerColor, depth: u32, ply: u32, mut alpha: i32, beta: i32,
+             prev_pv: &[Move], pv: &mut Vec<Move>) -> i32 {
+    if depth == 0 {
+      return self.quiesce(board, color, ply, self.quiescence, alpha, beta);
+    }
+    self.nodes += 1;
+    // from the first node on, so that a search with no time left at all stops at once
+    if self.nodes & 1023 == 1 && self.out_of_budget() {
+      self.aborted = true;
+    }
+    if self.aborted {
+      return 0;
+    }
+
+    let moves = legal_moves(board, color);
+    if moves.is_empty() {
+      return -MATE + ply as i32;
+    }
+
+    // a stored score decides the node only when it falls outside the window, so the principal
+    // variation is always searched out in full
+    let key = position_key(board, color);
+    let entry = self.table.probe(key);
+    if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+      let score = score_from_table(entry.score, ply);
+      let cutoff = match entry.bound {
+        Bound::Exact => score >= beta || score <= alpha,
+        Bound::Lower => score >= beta,
+        Bound::Upper => score <= alpha,
+      };
+      if cutoff {
+        return score;
+      }
+    }
+
+    // try the previous iteration's choice first, or else the stored best move
+    let mut order: Vec<usize> = (0..moves.len()).collect();
+    let pv_move = prev_pv.first().and_then(|pv_move| moves.iter().position(|mv| mv == pv_move));
+    let first = pv_move.or_else(|| entry.and_then(|entry| entry.best).map(usize::from).filter(|&i| i < moves.len()));
+    if let Some(first) = first {
+      order.swap(0, first);
+    }
+
+    let alpha_start = alpha;
+    let mut best = -MATE - 1;
+    let mut best_index = 0;
+    let mut line = Vec::new();
+    for (i, &index) in order.iter().enumerate() {
+      let mv = &moves[index];
+      let mut next = *board;
+      next.apply(color, mv);
+      self.enter_child(ply, board, &next);
+      let child_pv = if i == 0 && pv_move.is_some() { &prev_pv[1..] } else { &[] };
+      line.clear();
+      let score = -self.negamax(&next, !color, depth - 1, ply + 1, -beta, -alpha, child_pv, &mut line);
+      if self.aborted {
+        return 0;
+      }
+      if score > best {
+        best = score;
+        best_index = index;
+        if score > alpha {
+          alpha = score;
+          pv.clear();
+          pv.push(mv.clone());
+          pv.extend_from_slice(&line);
+        }
+        if alpha >= beta {
+          break;
+        }
+      }
+    }
+
+    let bound = if best >= beta {
+      Bound::Lower
+    } else if best > alpha_start {
+      Bound::Exact
+    } else {
+      Bound::Upper
+    };
+    self.table.store(key, TableEntry { score: score_to_table(best, ply), depth, bound, best: Some(best_index as u8) });
+    best
+  }
+
+  /**
+  Searches only captures from a leaf for as long as the side to move has one, at most `depth`
+  more plies, so that exchanges under way are played out before the position is judged. A jump is
+  forced, so a side with one cannot stand pat on the evaluation as in chess.
+   */
+  fn quiesce(&mut self, board: &Checkerboard, color: PlayerColor, ply: u32, depth: u32, mut alpha: i32, beta: i32) -> i32 {
+    self.nodes += 1;
+    if self.nodes & 1023 == 1 && self.out_of_budget() {
+      self.aborted = true;
+    }
+    if self.aborted {
+      return 0;
+    }
+
+    if color_captures(board, color).is_empty() {
+      if color_moves(board, color).is_empty() {
+        return -MATE + ply as i32;
+      }
+      return self.evaluate(board, color, ply);
+    }
+    if depth == 0 {
+      return self.evaluate(board, color, ply);
+    }
+
+    let mut best = -MATE - 1;
+    for mv in legal_moves(board, color) {
+      let mut next = *board;
+      next.apply(color, &mv);
+      self.enter_child(ply, board, &next);
+      let score = -self.quiesce(&next, !color, ply + 1, depth - 1, -beta, -alpha);
+      if self.aborted {
+        return 0;
+      }
+      if score > best {
+        best = score;
+        alpha = alpha.max(score);
+        if alpha >= beta {
+          break;
+        }
+      }
+    }
+    best
+  }
+
+  fn out_of_budget(&self) -> bool {
+    self.stop.load(Ordering::Relaxed)
+        || self.limits.time.is_some_and(|time| self.start.elapsed() >= time)
+        || self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes)
+        || self.time_manager.as_ref().is_some_and(|manager| self.start.elapsed() >= manager.maximum())
+  }
+}
+
+/// Makes a forced win or loss relative to the node storing it, as the node may be met at another ply.
+fn score_to_table(score: i32, ply: u32) -> i32 {
+  match score {
+    score if score > MATE_BOUND => score + ply as i32,
+    score if score < -MATE_BOUND => score - ply as i32,
+    score => score,
+  }
+}
+
+fn score_from_table(score: i32, ply: u32) -> i32 {
+  match score {
+    score if score > MATE_BOUND => score - ply as i32,
+    score if score < -MATE_BOUND => score + ply as i32,
+    score => score,
+  }
+}
+
+/// Formats a score for display: pieces as `+1.25`, forced wins as `#7` (plies to the win).
+pub fn format_score(score: i32) -> String {
+  if score > MATE_BOUND {
+    format!("#{}", MATE - score)
+  } else if score < -MATE_BOUND {
+    format!("#-{}", MATE + score)
+  } else {
+    format!("{:+.2}", score as f64 / 100.0)
+  }
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use crate::engine::search::SearchInfo;
+use crate::game::moves::Move;
+
+/// Kept back from every budget for the time between the search stopping and the clock being pressed.
+pub const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+/// Moves assumed to be left to play when the time control does not say; endings with kings can
+/// go on for a long time.
+const DEFAULT_MOVES_TO_GO: u32 = 40;
+/// The hard limit is at most this many times the planned time for a move.
+const MAX_STRETCH: u32 = 4;
+/// Score drop, in hundredths of a man, that doubles the time for a move.
+const DROP_SCALE: f64 = 150.0;
+
+/// The clock of the side to move, for the search to plan its own time.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClockTime {
+  pub remaining: Duration,
+  /// Added to the remaining time after the move.
+  pub increment: Duration,
+  /// Moves to play before the clock is topped up, if the time control has periods.
+  pub moves_to_go: Option<u32>,
+}
+
+/**
+Decides how long a search with a clock may run. It plans a share of the remaining time, plus most
+of the increment, and a hard limit the search is stopped at however it stands. Between iterations
+the planned time is stretched while the best move keeps changing or the score falls, up to the
+hard limit. Both leave a margin of the remaining time, so the engine never loses on time.
+ */
+#[derive(Clone, Debug)]
+pub struct TimeManager {
+  optimum: Duration,
+  maximum: Duration,
+  /// The previous iteration's best move.
+  best: Option<Move>,
+  /// The scores of the iterations so far.
+  scores: Vec<i32>,
+  /// Best move changes, halved every iteration so that recent ones count most.
+  changes: f64,
+  /// The largest score drop seen, compared with the iteration two before as scores swing
+  /// between odd and even depths.
+  drop: i32,
+}
+
+impl TimeManager {
+  pub fn new(clock: &ClockTime) -> TimeManager {
+    let available = clock.remaining.saturating_sub(MOVE_OVERHEAD);
+    let moves = clock.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+    // the last move before a new period may use most of what is left
+    let cap = if moves == 1 { available.mul_f64(0.9) } else { available / 4 };
+    let optimum = (available / moves + clock.increment * 3 / 4).min(cap);
+    TimeManager { optimum, maximum: (optimum * MAX_STRETCH).min(cap), best: None, scores: Vec::new(), changes: 0.0, drop: 0 }
+  }
+
+  /// The time planned for the move when the search is stable.
+  pub fn optimum(&self) -> Duration {
+    self.optimum
+  }
+
+  /// The time after which the search is stopped.
+  pub fn maximum(&self) -> Duration {
+    self.maximum
+  }
+
+  /**
+  Takes in a completed iteration, after `elapsed` in all, and tells whether to stop. Another
+  iteration usually takes longer than all before it, so none is started once half the stretched
+  planned time is used.
+   */
+  pub fn iteration_done(&mut self, info: &SearchInfo, elapsed: Duration) -> bool {
+    self.changes /= 2.0;
+    if self.best.is_some() && info.best_move() != self.best.as_ref() {
+      self.changes += 1.0;
+    }
+    self.best = info.best_move().cloned();
+    if let Some(&score) = self.scores.iter().rev().nth(1) {
+      self.drop = self.drop.max(score - info.score);
+    }
+    self.scores.push(info.score);
+    let stretch = (1.0 + self.changes) * (1.0 + self.drop.max(0) as f64 / DROP_SCALE);
+    elapsed * 2 >= self.optimum.mul_f64(stretch).min(self.maximum)
+  }
+}
@@ -0,0 +1,341 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use eyre::{eyre, Result, WrapErr};
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::engine::network::{active_inputs, HIDDEN, INPUTS, Network};
+use crate::engine::search::{SearchInfo, SearchLimits, Searcher};
+use crate::game::{Checkerboard, PlayerColor};
+use crate::game::bitboard::Bitboard;
+use crate::game::moves::{color_captures, legal_moves, Move};
+use crate::game::notation::to_fen;
+
+/// Score, in hundredths of a man, at which the network's winning chance is about 73%.
+const SCORE_SCALE: f32 = 200.0;
+/// Search scores are capped at this size, so forced wins count as clearly won positions.
+const SCORE_CAP: i32 = 2000;
+/// Decay rates of Adam's averages of the gradient and of its square.
+const BETA1: f32 = 0.9;
+const BETA2: f32 = 0.999;
+/// Start of a binary data file, followed by the format version.
+const DATA_MAGIC: &[u8; 4] = b"CKSP";
+const DATA_VERSION: u32 = 1;
+/// Size of a position in a binary data file.
+const RECORD_SIZE: usize = 20;
+
+/// A position from a self-play game, labeled for training.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+  pub board: Checkerboard,
+  pub color: PlayerColor,
+  /// The shallow search's score from White's point of view.
+  pub score: i32,
+  /// The game's result for White: 1 for a win, 0.5 for a draw, 0 for a loss.
+  pub outcome: f32,
+}
+
+/// How self-play games for training are played.
+#[derive(Clone, Debug)]
+pub struct SelfPlayOptions {
+  pub depth: u32,
+  /// The first plies of each game are random, so games differ.
+  pub random_plies: u32,
+  /// Games still running after this many plies are counted as draws.
+  pub max_plies: u32,
+  /// Spread of the move choice in hundredths of a man: moves are picked with probability
+  /// proportional to `exp(score / temperature)`. Zero always plays the best move.
+  pub temperature: f32,
+  /// The temperature only applies to moves before this ply; later the best move is played.
+  pub temperature_plies: u32,
+  /// Evaluates positions in the games' searches instead of the handwritten evaluation, so a
+  /// network can be trained further on its own games.
+  pub network: Option<Arc<Network>>,
+}
+
+impl Default for SelfPlayOptions {
+  fn default() -> Self {
+    SelfPlayOptions {
+      depth: 4,
+      random_plies: 6,
+      max_plies: 200,
+      temperature: 0.0,
+      temperature_plies: 0,
+      network: None,
+    }
+  }
+}
+
+/**
+Plays one game of the engine against itself from the position after `opening` and labels its
+positions with the search score and the final result. Positions where the side to move has to
+capture are left out, as their score depends on the exchange under way rather than on the
+position.
+ */
+pub fn play_training_game(options: &SelfPlayOptions, opening: &[Move], rng: &mut impl Rng) -> Vec<Sample> {
+  let mut board = Checkerboard::new();
+  let mut color = PlayerColor::White;
+  for mv in opening {
+    board.apply(color, mv);
+    color = !color;
+  }
+  let mut searcher = Searcher::new(SearchLimits::depth(options.depth)).with_network(options.network.clone());
+  let mut samples = Vec::new();
+  let mut winner = None;
+  let random_plies = opening.len() as u32 + options.random_plies;
+  for ply in opening.len() as u32..options.max_plies {
+    let moves = legal_moves(&board, color);
+    if moves.is_empty() {
+      winner = Some(!color);
+      break;
+    }
+    let mv = if ply < random_plies {
+      moves[rng.gen_range(0..moves.len())].clone()
+    } else {
+      let (score, mv) = if options.temperature > 0.0 && ply < options.temperature_plies {
+        let lines = searcher.search_lines(&board, color, moves.len(), |_| {});
+        (lines[0].score, choose_by_temperature(&lines, options.temperature, rng).cloned())
+      } else {
+        let info = searcher.search(&board, color);
+        (info.score, info.best_move().cloned())
+      };
+      if color_captures(&board, color).is_empty() {
+        let score = score.clamp(-SCORE_CAP, SCORE_CAP);
+        let score = if color == PlayerColor::White { score } else { -score };
+        samples.push(Sample { board, color, score, outcome: 0.5 });
+      }
+      mv.unwrap_or_else(|| moves[0].clone())
+    };
+    board.apply(color, &mv);
+    color = !color;
+  }
+  let outcome = match winner {
+    Some(PlayerColor::White) => 1.0,
+    Some(PlayerColor::Black) => 0.0,
+    None => 0.5,
+  };
+  for sample in &mut samples {
+    sample.outcome = outcome;
+  }
+  samples
+}
+
+/**
+Picks the first move of one of `lines` at random, with probability proportional to
+`exp(score / temperature)`, so moves scoring `temperature` less than another are picked about
+e times less often. Returns `None` if there are no lines.
+ */
+pub fn choose_by_temperature<'a>(lines: &'a [SearchInfo], temperature: f32, rng: &mut impl Rng) -> Option<&'a Move> {
+  let best = lines.iter().map(|line| line.score).max()?;
+  let weights: Vec<f32> = lines.iter().map(|line| ((line.score - best) as f32 / temperature).exp()).collect();
+  let mut pick = rng.gen_range(0.0..weights.iter().sum::<f32>());
+  for (line, weight) in lines.iter().zip(&weights) {
+    if pick < *weight {
+      return line.best_move();
+    }
+    pick -= weight;
+  }
+  lines.last().and_then(SearchInfo::best_move)
+}
+
+/// How positions are written to a data file.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DataFormat {
+  /**
+  The magic `CKSP` and the version as a little-endian `u32`, then 20 bytes per position: the
+  white men, black men, white kings and black kings as little-endian `u32`s with a bit per
+  playable square, the side to move (0 White, 1 Black), the score as an `i16` and the result for
+  White (0 a loss, 1 a draw, 2 a win). The squares go row by row from White's side: bits 0-3 are
+  squares 29-32, bits 4-7 squares 25-28 and so on up to squares 1-4 in bits 28-31, so bit `i` is
+  square `4 * (7 - i / 4) + i % 4 + 1`.
+   */
+  Binary,
+  /// A JSON object per line with the position as `fen`, the side to move as `turn`, `score` and `result`.
+  Jsonl,
+}
+
+impl DataFormat {
+  /// What a file in this format starts with.
+  pub fn header(self) -> Vec<u8> {
+    match self {
+      DataFormat::Binary => DATA_MAGIC.iter().copied().chain(DATA_VERSION.to_le_bytes()).collect(),
+      DataFormat::Jsonl => Vec::new(),
+    }
+  }
+}
+
+impl Sample {
+  /// The sample as written in `format`, without the file's header.
+  pub fn to_bytes(&self, format: DataFormat) -> Vec<u8> {
+    match format {
+      DataFormat::Binary => {
+        let mut bytes = Vec::with_capacity(RECORD_SIZE);
+        for (color, king) in [(PlayerColor::White, false), (PlayerColor::Black, false), (PlayerColor::White, true), (PlayerColor::Black, true)] {
+          let pieces = if king { self.board.kings(color) } else { self.board.men(color) };
+          let dense = pieces.squares().fold(0u32, |dense, square| dense | 1 << (square.index() >> 1));
+          bytes.extend(dense.to_le_bytes());
+        }
+        bytes.push(if self.color == PlayerColor::White { 0 } else { 1 });
+        bytes.extend((self.score as i16).to_le_bytes());
+        bytes.push((self.outcome * 2.0) as u8);
+        bytes
+      }
+      DataFormat::Jsonl => {
+        let turn = if self.color == PlayerColor::White { "white" } else { "black" };
+        format!("{{\"fen\":\"{}\",\"turn\":\"{}\",\"score\":{},\"result\":{}}}\n",
+                to_fen(&self.board, self.color), turn, self.score, self.outcome).into_bytes()
+      }
+    }
+  }
+
+  fn from_record(record: &[u8]) -> Result<Sample> {
+    let set = |at: usize| {
+      let dense = u32::from_le_bytes(record[at..at + 4].try_into().unwrap());
+      // dark squares are the odd bits on odd ranks and the even bits on even ranks
+      (0..32).filter(|i| dense & 1 << i != 0).fold(Bitboard::new(), |set, i| set | 1u64 << (2 * i + (i >> 2 & 1)))
+    };
+    let sets = [set(0), set(4), set(8), set(12)];
+    if sets.iter().map(Bitboard::count).sum::<u32>() != sets.iter().fold(Bitboard::new(), |all, &set| all | set).count() {
+      return Err(eyre!("two pieces on one square"));
+    }
+    let board = Checkerboard::from_pieces(sets[0], sets[1], sets[2], sets[3]);
+    board.validate().map_err(|e| eyre!("invalid position: {}", e))?;
+    let color = match record[16] {
+      0 => PlayerColor::White,
+      1 => PlayerColor::Black,
+      other => return Err(eyre!("invalid side to move {}", other)),
+    };
+    let score = i16::from_le_bytes([record[17], record[18]]) as i32;
+    if record[19] > 2 {
+      return Err(eyre!("invalid result {}", record[19]));
+    }
+    Ok(Sample { board, color, score, outcome: record[19] as f32 / 2.0 })
+  }
+}
+
+/// Reads the samples of a binary data file.
+pub fn read_samples(bytes: &[u8]) -> Result<Vec<Sample>> {
+  if bytes.len() < 8 || &bytes[..4] != DATA_MAGIC {
+    return Err(eyre!("not a binary self-play data file"));
+  }
+  let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+  if version != DATA_VERSION {
+    return Err(eyre!("unsupported data version {}", version));
+  }
+  if !(bytes.len() - 8).is_multiple_of(RECORD_SIZE) {
+    return Err(eyre!("truncated data"));
+  }
+  bytes[8..].chunks(RECORD_SIZE).enumerate()
+      .map(|(i, record)| Sample::from_record(record).wrap_err_with(|| format!("position {}", i + 1)))
+      .collect()
+}
+
+pub fn load_samples(path: &Path) -> Result<Vec<Sample>> {
+  let bytes = fs::read(path).wrap_err_with(|| format!("could not read {}", path.display()))?;
+  read_samples(&bytes).wrap_err_with(|| format!("invalid data file {}", path.display()))
+}
+
+#[derive(Clone, Debug)]
+pub struct TrainingOptions {
+  pub epochs: u32,
+  /// Step size of the Adam optimizer.
+  pub learning_rate: f32,
+  /// How much the game result counts in the target against the search score, from 0 to 1.
+  pub outcome_weight: f32,
+}
+
+impl Default for TrainingOptions {
+  fn default() -> Self {
+    TrainingOptions { epochs: 10, learning_rate: 0.005, outcome_weight: 0.3 }
+  }
+}
+
+fn sigmoid(x: f32) -> f32 {
+  1.0 / (1.0 + (-x).exp())
+}
+
+/// Adam's running averages of the gradient and its square for every weight, laid out like a network.
+struct Moments {
+  mean: Network,
+  square: Network,
+  steps: i32,
+}
+
+impl Moments {
+  fn new() -> Moments {
+    let zero = Network {
+      input_weights: vec![0.0; INPUTS * HIDDEN],
+      hidden_bias: vec![0.0; HIDDEN],
+      output_weights: vec![0.0; HIDDEN],
+      output_bias: 0.0,
+    };
+    Moments { mean: zero.clone(), square: zero, steps: 0 }
+  }
+}
+
+/// Moves `weight` against `gradient` by Adam's rule, given its moments and the bias corrections.
+fn adam_step(weight: &mut f32, mean: &mut f32, square: &mut f32, gradient: f32, rate: f32, corrections: (f32, f32)) {
+  *mean = BETA1 * *mean + (1.0 - BETA1) * gradient;
+  *square = BETA2 * *square + (1.0 - BETA2) * gradient * gradient;
+  *weight -= rate * (*mean / corrections.0) / ((*square / corrections.1).sqrt() + 1e-8);
+}
+
+/**
+Trains `network` on `samples` with the Adam optimizer, one sample at a time, calling `on_epoch`
+with each epoch's number and mean loss. Outputs and targets are compared as winning chances: the
+target mixes the result with the search score, both squashed by the same logistic curve as the
+output. Input weights are only updated for the pieces on the board.
+ */
+pub fn train(network: &mut Network, samples: &[Sample], options: &TrainingOptions, rng: &mut impl Rng,
+             mut on_epoch: impl FnMut(u32, f32)) {
+  let mut moments = Moments::new();
+  let mut order: Vec<usize> = (0..samples.len()).collect();
+  for epoch in 1..=options.epochs {
+    order.shuffle(rng);
+    let mut total = 0.0;
+    for &i in &order {
+      total += train_sample(network, &mut moments, &samples[i], options);
+    }
+    on_epoch(epoch, total / samples.len().max(1) as f32);
+  }
+}
+
+/// Takes one gradient step on `sample` and returns its squared error before the step.
+fn train_sample(network: &mut Network, moments: &mut Moments, sample: &Sample, options: &TrainingOptions) -> f32 {
+  let inputs: Vec<usize> = active_inputs(&sample.board).collect();
+  let mut hidden = network.hidden_bias.clone();
+  for &input in &inputs {
+    for (value, weight) in hidden.iter_mut().zip(&network.input_weights[input * HIDDEN..(input + 1) * HIDDEN]) {
+      *value += weight;
+    }
+  }
+  let predicted = sigmoid(network.output(&hidden) / SCORE_SCALE);
+  let target = options.outcome_weight * sample.outcome
+      + (1.0 - options.outcome_weight) * sigmoid(sample.score as f32 / SCORE_SCALE);
+  let error = predicted - target;
+
+  moments.steps += 1;
+  let corrections = (1.0 - BETA1.powi(moments.steps), 1.0 - BETA2.powi(moments.steps));
+  let rate = options.learning_rate;
+  let (mean, square) = (&mut moments.mean, &mut moments.square);
+  let output_gradient = 2.0 * error * predicted * (1.0 - predicted) / SCORE_SCALE;
+  adam_step(&mut network.output_bias, &mut mean.output_bias, &mut square.output_bias, output_gradient, rate, corrections);
+  for (j, &value) in hidden.iter().enumerate() {
+    if value <= 0.0 {
+      continue;
+    }
+    let hidden_gradient = output_gradient * network.output_weights[j];
+    adam_step(&mut network.output_weights[j], &mut mean.output_weights[j], &mut square.output_weights[j],
+              output_gradient * value, rate, corrections);
+    adam_step(&mut network.hidden_bias[j], &mut mean.hidden_bias[j], &mut square.hidden_bias[j],
+              hidden_gradient, rate, corrections);
+    for &input in &inputs {
+      let k = input * HIDDEN + j;
+      adam_step(&mut network.input_weights[k], &mut mean.input_weights[k], &mut square.input_weights[k],
+                hidden_gradient, rate, corrections);
+    }
+  }
+  error * error
+}
@@ -0,0 +1,138 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::game::{Checkerboard, PlayerColor};
+
+/// Number of entries in a table made with `TranspositionTable::default`, 16 bytes each.
+const DEFAULT_ENTRIES: usize = 1 << 18;
+
+/// Zobrist keys: one per piece kind (as numbered by `Checkerboard::index`) and square.
+const PIECE_KEYS: [[u64; 64]; 4] = piece_keys();
+const BLACK_TO_MOVE: u64 = splitmix(0xB1AC);
+
+/// A step of the SplitMix64 generator, used to make the keys at compile time.
+const fn splitmix(state: u64) -> u64 {
+  let mut z = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+  z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+  z ^ (z >> 31)
+}
+
+const fn piece_keys() -> [[u64; 64]; 4] {
+  let mut keys = [[0; 64]; 4];
+  let mut state = 0;
+  let mut kind = 0;
+  while kind < 4 {
+    let mut square = 0;
+    while square < 64 {
+      state = splitmix(state);
+      keys[kind][square] = state;
+      square += 1;
+    }
+    kind += 1;
+  }
+  keys
+}
+
+/// A hash of the pieces on `board` and the side to move.
+pub fn position_key(board: &Checkerboard, color: PlayerColor) -> u64 {
+  let mut key = if color == PlayerColor::Black { BLACK_TO_MOVE } else { 0 };
+  for side in [PlayerColor::White, PlayerColor::Black] {
+    for (king, pieces) in [(false, board.men(side)), (true, board.kings(side))] {
+      let keys = &PIECE_KEYS[Checkerboard::index(side, king)];
+      for square in pieces.squares() {
+        key ^= keys[square.index() as usize];
+      }
+    }
+  }
+  key
+}
+
+/// How a stored score relates to the position's true score.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Bound {
+  Exact,
+  /// The true score is at least the stored one: the search failed high.
+  Lower,
+  /// The true score is at most the stored one: the search failed low.
+  Upper,
+}
+
+/// What a search found out about a position.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TableEntry {
+  pub score: i32,
+  pub depth: u32,
+  pub bound: Bound,
+  /// Index of the best move in `legal_moves` order, if one was found.
+  pub best: Option<u8>,
+}
+
+impl TableEntry {
+  fn pack(&self) -> u64 {
+    let bound = match self.bound {
+      Bound::Exact => 0,
+      Bound::Lower => 1,
+      Bound::Upper => 2,
+    };
+    let best = self.best.map_or(0xFF, u64::from);
+    (self.score as u32 as u64) | ((self.depth.min(0xFF) as u64) << 32) | (bound << 40) | (best << 42)
+  }
+
+  fn unpack(data: u64) -> TableEntry {
+    let bound = match (data >> 40) & 3 {
+      0 => Bound::Exact,
+      1 => Bound::Lower,
+      _ => Bound::Upper,
+    };
+    let best = ((data >> 42) & 0xFF) as u8;
+    TableEntry {
+      score: data as u32 as i32,
+      depth: ((data >> 32) & 0xFF) as u32,
+      bound,
+      best: (best != 0xFF).then_some(best),
+    }
+  }
+}
+
+/**
+A hash table of search results shared by any number of threads without locking. Each slot holds
+the entry and the key xor-ed with it, so an entry torn by two threads writing at once fails the
+key check and reads as missing. New entries always replace old ones.
+ */
+pub struct TranspositionTable {
+  slots: Vec<[AtomicU64; 2]>,
+}
+
+impl TranspositionTable {
+  /// Makes a table with `entries` slots, rounded up to a power of two.
+  pub fn new(entries: usize) -> TranspositionTable {
+    let entries = entries.max(1).next_power_of_two();
+    TranspositionTable { slots: (0..entries).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect() }
+  }
+
+  pub fn probe(&self, key: u64) -> Option<TableEntry> {
+    let slot = &self.slots[key as usize & (self.slots.len() - 1)];
+    let (check, data) = (slot[0].load(Ordering::Relaxed), slot[1].load(Ordering::Relaxed));
+    (data != 0 && check ^ data == key).then(|| TableEntry::unpack(data))
+  }
+
+  pub fn store(&self, key: u64, entry: TableEntry) {
+    let slot = &self.slots[key as usize & (self.slots.len() - 1)];
+    let data = entry.pack();
+    slot[0].store(key ^ data, Ordering::Relaxed);
+    slot[1].store(data, Ordering::Relaxed);
+  }
+
+  pub fn clear(&self) {
+    for slot in &self.slots {
+      slot[0].store(0, Ordering::Relaxed);
+      slot[1].store(0, Ordering::Relaxed);
+    }
+  }
+}
+
+impl Default for TranspositionTable {
+  fn default() -> Self {
+    TranspositionTable::new(DEFAULT_ENTRIES)
+  }
+}
@@ -8,11 +8,11 @@ pub const TOP_EDGE: Bitboard = Bitboard::from_u64(BOTTOM_EDGE.bb << 56);
 
 pub const LEFT_TWO: Bitboard = Bitboard::from_u64(LEFT_EDGE.bb | (LEFT_EDGE.bb << 9));
 pub const RIGHT_TWO: Bitboard = Bitboard::from_u64(RIGHT_EDGE.bb | (RIGHT_EDGE.bb >> 9));
-pub const BOTTOM_TWO: Bitboard = Bitboard::from_u64(BOTTOM_EDGE.bb | (BOTTOM_EDGE.bb << 9));
-pub const TOP_TWO: Bitboard = Bitboard::from_u64(TOP_EDGE.bb | (TOP_EDGE.bb >> 9));
+pub const BOTTOM_TWO: Bitboard = Bitboard::from_u64(BOTTOM_EDGE.bb | (BOTTOM_EDGE.bb << 8));
+pub const TOP_TWO: Bitboard = Bitboard::from_u64(TOP_EDGE.bb | (TOP_EDGE.bb >> 8));
 
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
 pub struct Bitboard {
   bb: u64,
 }
@@ -60,6 +60,38 @@ impl Bitboard { /** Creates a new empty bitboard. */
       self.bb.trailing_zeros() as i32
     }
   }
+
+  pub const fn count(&self) -> u32 {
+    self.bb.count_ones()
+  }
+
+  pub const fn to_u64(&self) -> u64 {
+    self.bb
+  }
+
+  /// Iterates over the occupied squares of this bitboard as single-square bitboards, from the
+  /// lowest index to the highest.
+  pub fn squares(self) -> Squares {
+    Squares { bb: self.bb }
+  }
+}
+
+pub struct Squares {
+  bb: u64,
+}
+
+impl Iterator for Squares {
+  type Item = Bitboard;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.bb == 0 {
+      None
+    } else {
+      let square = self.bb & self.bb.wrapping_neg();
+      self.bb &= self.bb - 1;
+      Some(Bitboard::from(square))
+    }
+  }
 }
 
 impl From<u64> for Bitboard {
@@ -2,31 +2,44 @@ use std::fmt::{Display, Formatter};
 use std::ops::Not;
 
 use crate::game::bitboard::{Bitboard, BOTTOM_EDGE, TOP_EDGE};
-use crate::game::moves::{color_captures, piece_captures, piece_moves};
+use crate::game::moves::{capturing_pieces, color_captures, piece_captures, piece_moves, Move};
+use crate::game::notation::format_squares;
 
 pub mod bitboard;
 pub mod moves;
+pub mod notation;
+pub mod pdn;
 
 const WHITE_MEN: usize = 0;
 const BLACK_MEN: usize = 1;
 const WHITE_KINGS: usize = 2;
 const BLACK_KINGS: usize = 3;
 
+/// Each side starts with 12 men and can never have more pieces.
+pub const MAX_PIECES: u32 = 12;
+
 const WHITE_MAN_SYM: char = '○';
 const BLACK_MAN_SYM: char = '●';
 const WHITE_KING_SYM: char = '☆';
 const BLACK_KING_SYM: char = '★';
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub struct Checkerboard {
   pieces: [Bitboard; 4],
+  /// The player and square of a piece part way through a capture sequence, which must keep
+  /// capturing before anything else can move.
+  pending: Option<(PlayerColor, Bitboard)>,
 }
 
 impl Checkerboard {
   pub fn new() -> Checkerboard {
     let rm = Bitboard::from(0x55AA55);
     let bm = Bitboard::from(0xAA55AA << 40);
-    Checkerboard { pieces: [rm, bm, Bitboard::new(), Bitboard::new()] }
+    Checkerboard { pieces: [rm, bm, Bitboard::new(), Bitboard::new()], pending: None }
+  }
+
+  pub fn from_pieces(white_men: Bitboard, black_men: Bitboard, white_kings: Bitboard, black_kings: Bitboard) -> Checkerboard {
+    Checkerboard { pieces: [white_men, black_men, white_kings, black_kings], pending: None }
   }
 
   pub fn index(color: PlayerColor, king: bool) -> usize {
@@ -40,24 +53,42 @@ impl Checkerboard {
 
   /**
   Attempts to make a move. Returns the color of the player who will make next move (same color
-  if move is invalid or extra captures available).
+  if extra captures available), or the reason the move is not allowed.
    */
   pub fn make_move(&mut self, color: PlayerColor, start_square: Bitboard, end_square: Bitboard) -> MoveResult {
     // this function only works for single squares
     if !start_square.is_single_square() || !end_square.is_single_square() {
-      return MoveResult::Invalid;
+      return MoveResult::Invalid(MoveError::NotSingleSquare);
+    }
+
+    if let Some((pending_color, square)) = self.pending {
+      if color != pending_color {
+        return MoveResult::Invalid(MoveError::NotYourTurn(pending_color));
+      }
+      if start_square != square {
+        return MoveResult::Invalid(MoveError::MustContinueCapture(square));
+      }
     }
 
     let king = (start_square & self.kings(color)).is_not_empty();
     if !king && (start_square & self.men(color)).is_empty() {
-      return MoveResult::Invalid;
+      return MoveResult::Invalid(MoveError::NotYourPiece(start_square));
+    }
+    if let Err(error) = self.check_geometry(color, king, start_square, end_square) {
+      return MoveResult::Invalid(error);
     }
 
     let move_bb = piece_moves(self, color, king, start_square) & end_square;
     let capture_bb = piece_captures(self, color, king, start_square) & end_square;
+    if capture_bb.is_empty() && self.pending.is_some() {
+      return MoveResult::Invalid(MoveError::MustContinueCapture(start_square));
+    }
     let must_capture = color_captures(self, color).is_not_empty();
-    if (capture_bb | move_bb).is_empty() || capture_bb.is_empty() && must_capture {
-      return MoveResult::Invalid;
+    if capture_bb.is_empty() && must_capture {
+      return MoveResult::Invalid(MoveError::CaptureMandatory(capturing_pieces(self, color)));
+    }
+    if (capture_bb | move_bb).is_empty() {
+      return MoveResult::Invalid(MoveError::NotDiagonal);
     }
 
     let index = Checkerboard::index(color, king);
@@ -74,6 +105,7 @@ impl Checkerboard {
     };
 
     self.pieces[index] &= !start_square;
+    self.pending = None;
     let promotion_edge = match color {
       PlayerColor::White => TOP_EDGE,
       PlayerColor::Black => BOTTOM_EDGE
@@ -84,6 +116,7 @@ impl Checkerboard {
     } else {
       self.pieces[index] |= end_square;
       if more_captures {
+        self.pending = Some((color, end_square));
         MoveResult::Valid(color)
       } else {
         MoveResult::Valid(!color)
@@ -91,6 +124,62 @@ impl Checkerboard {
     }
   }
 
+  /// The player and square of a piece that is part way through a capture sequence, if any.
+  pub fn pending_capture(&self) -> Option<(PlayerColor, Bitboard)> {
+    self.pending
+  }
+
+  /// Checks that a step is a one-square diagonal move or a jump over an opposing piece.
+  fn check_geometry(&self, color: PlayerColor, king: bool, start_square: Bitboard, end_square: Bitboard) -> Result<(), MoveError> {
+    if (end_square & self.empty()).is_empty() {
+      return Err(MoveError::DestinationOccupied(end_square));
+    }
+    let (start, end) = (start_square.index(), end_square.index());
+    let rank_delta = (end >> 3) - (start >> 3);
+    let file_delta = (end & 7) - (start & 7);
+    if rank_delta.abs() != file_delta.abs() || !(1..=2).contains(&rank_delta.abs()) {
+      return Err(MoveError::NotDiagonal);
+    }
+    let forward = match color {
+      PlayerColor::White => rank_delta > 0,
+      PlayerColor::Black => rank_delta < 0,
+    };
+    if !king && !forward {
+      return Err(MoveError::MenCannotMoveBackwards);
+    }
+    if rank_delta.abs() == 2 && (Bitboard::midsquare(start_square, end_square) & self.opponents(color)).is_empty() {
+      return Err(MoveError::NothingToCapture);
+    }
+    Ok(())
+  }
+
+  /**
+  Plays every step of a complete move. Returns the color of the player who will make the next move,
+  or the reason the move is not allowed (leaving the board untouched) if any step is rejected or the
+  jump chain is incomplete.
+   */
+  pub fn apply(&mut self, color: PlayerColor, mv: &Move) -> MoveResult {
+    let mut board = *self;
+    let mut result = MoveResult::Invalid(MoveError::NotSingleSquare);
+    for (start, end) in mv.steps() {
+      if result == MoveResult::Valid(!color) {
+        return MoveResult::Invalid(MoveError::NoMoreCaptures(start));
+      }
+      result = board.make_move(color, start, end);
+      if let MoveResult::Invalid(_) = result {
+        return result;
+      }
+    }
+    match result {
+      MoveResult::Valid(next) if next == color => MoveResult::Invalid(MoveError::MustContinueCapture(mv.end())),
+      MoveResult::Valid(_) => {
+        *self = board;
+        result
+      }
+      MoveResult::Invalid(_) => result,
+    }
+  }
+
   pub fn men(&self, color: PlayerColor) -> Bitboard {
     match color {
       PlayerColor::White => self.pieces[WHITE_MEN],
@@ -121,6 +210,41 @@ impl Checkerboard {
     )
   }
 
+  /**
+  Puts a piece of the given color, a king if `king` is set, on `square`, replacing whatever was
+  there, or empties the square if `piece` is `None`. Any unfinished capture sequence is dropped.
+   */
+  pub fn set_square(&mut self, square: Bitboard, piece: Option<(PlayerColor, bool)>) {
+    for pieces in &mut self.pieces {
+      *pieces &= !square;
+    }
+    if let Some((color, king)) = piece {
+      self.pieces[Checkerboard::index(color, king)] |= square;
+    }
+    self.pending = None;
+  }
+
+  /// Checks that the position could arise in a game: see `PositionError`.
+  pub fn validate(&self) -> Result<(), PositionError> {
+    let dark_squares = Bitboard::from(0xAA55_AA55_AA55_AA55);
+    let light = !self.empty() & !dark_squares;
+    if light.is_not_empty() {
+      return Err(PositionError::LightSquare(light));
+    }
+    let crowned = (self.men(PlayerColor::White) & TOP_EDGE) | (self.men(PlayerColor::Black) & BOTTOM_EDGE);
+    if crowned.is_not_empty() {
+      return Err(PositionError::ManOnPromotionRow(crowned));
+    }
+    for color in [PlayerColor::White, PlayerColor::Black] {
+      match self.pieces(color).count() {
+        0 => return Err(PositionError::NoPieces(color)),
+        count if count > MAX_PIECES => return Err(PositionError::TooManyPieces(color)),
+        _ => {}
+      }
+    }
+    Ok(())
+  }
+
   pub fn raw_string(&self) -> String {
     let white_men = self.pieces[WHITE_MEN];
     let black_men = self.pieces[BLACK_MEN];
@@ -149,6 +273,12 @@ impl Checkerboard {
   }
 }
 
+impl Default for Checkerboard {
+  fn default() -> Self {
+    Checkerboard::new()
+  }
+}
+
 impl Display for Checkerboard {
   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
     let white_men = self.pieces[WHITE_MEN].to_string();
@@ -178,7 +308,7 @@ impl Display for Checkerboard {
   }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum PlayerColor {
   White,
   Black,
@@ -205,8 +335,71 @@ impl Not for PlayerColor {
   }
 }
 
+/// Why a set-up position is not one that could occur in a game.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PositionError {
+  /// Pieces on these light squares.
+  LightSquare(Bitboard),
+  /// Men on these squares of the far row should have been crowned.
+  ManOnPromotionRow(Bitboard),
+  TooManyPieces(PlayerColor),
+  NoPieces(PlayerColor),
+}
+
+impl Display for PositionError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      PositionError::LightSquare(_) => f.write_str("Pieces can only stand on the dark squares"),
+      PositionError::ManOnPromotionRow(squares) => write!(f, "Men on {} should be kings", format_squares(*squares)),
+      PositionError::TooManyPieces(color) => write!(f, "{} has more than {} pieces", color, MAX_PIECES),
+      PositionError::NoPieces(color) => write!(f, "{} has no pieces", color),
+    }
+  }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum MoveResult {
   Valid(PlayerColor),
-  Invalid,
+  Invalid(MoveError),
+}
+
+/// Why a move was rejected. Squares are single-square bitboards.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MoveError {
+  NotSingleSquare,
+  NotYourPiece(Bitboard),
+  DestinationOccupied(Bitboard),
+  NotDiagonal,
+  MenCannotMoveBackwards,
+  NothingToCapture,
+  /// A capture is available, so plain moves are not allowed. Holds every piece that can capture.
+  CaptureMandatory(Bitboard),
+  /// A capture sequence is in progress and must be continued by the piece on this square.
+  MustContinueCapture(Bitboard),
+  /// The other player is part way through a capture sequence.
+  NotYourTurn(PlayerColor),
+  /// The capture sequence already ended before this square.
+  NoMoreCaptures(Bitboard),
+  /// An abbreviated move matches more than one capture sequence.
+  Ambiguous,
+  GameOver,
+}
+
+impl Display for MoveError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      MoveError::NotSingleSquare => f.write_str("A move needs exactly one start and one end square"),
+      MoveError::NotYourPiece(square) => write!(f, "There is no piece of yours on {}", format_squares(*square)),
+      MoveError::DestinationOccupied(square) => write!(f, "Square {} is occupied", format_squares(*square)),
+      MoveError::NotDiagonal => f.write_str("Pieces move one square diagonally or jump over a piece"),
+      MoveError::MenCannotMoveBackwards => f.write_str("Men cannot move backwards"),
+      MoveError::NothingToCapture => f.write_str("There is no opposing piece to jump over"),
+      MoveError::CaptureMandatory(pieces) => write!(f, "Capture is mandatory: {} can capture", format_squares(*pieces)),
+      MoveError::MustContinueCapture(square) => write!(f, "You must continue capturing with the piece on {}", format_squares(*square)),
+      MoveError::NotYourTurn(color) => write!(f, "{} has not finished capturing", color),
+      MoveError::NoMoreCaptures(square) => write!(f, "The capture sequence is already over at {}", format_squares(*square)),
+      MoveError::Ambiguous => f.write_str("More than one capture sequence fits; give the intermediate squares"),
+      MoveError::GameOver => f.write_str("The game is over"),
+    }
+  }
 }
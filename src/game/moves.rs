@@ -1,5 +1,5 @@
 use crate::game::bitboard::*;
-use crate::game::{Checkerboard, PlayerColor};
+use crate::game::{Checkerboard, MoveError, MoveResult, PlayerColor};
 
 /*  Square chart
       57  59  61  63
@@ -66,3 +66,174 @@ pub fn color_captures(board: &Checkerboard, color: PlayerColor) -> Bitboard {
 
   piece_captures(board, color, false, men) | piece_captures(board, color, true, kings)
 }
+
+/// The pieces of `color` allowed to move: all of them, or only the capturing piece mid-sequence.
+fn movable_pieces(board: &Checkerboard, color: PlayerColor) -> Bitboard {
+  match board.pending_capture() {
+    Some((pending_color, square)) if pending_color == color => square,
+    Some(_) => Bitboard::new(),
+    None => board.pieces(color),
+  }
+}
+
+/// The pieces of `color` that have a capture available.
+pub fn capturing_pieces(board: &Checkerboard, color: PlayerColor) -> Bitboard {
+  let mut result = Bitboard::new();
+  for piece in movable_pieces(board, color).squares() {
+    let king = (piece & board.kings(color)).is_not_empty();
+    if piece_captures(board, color, king, piece).is_not_empty() {
+      result |= piece;
+    }
+  }
+  result
+}
+
+/**
+The squares the piece on `square` may step to next, taking mandatory captures and an unfinished
+capture sequence into account. Empty if the piece is not allowed to move.
+ */
+pub fn piece_destinations(board: &Checkerboard, color: PlayerColor, square: Bitboard) -> Bitboard {
+  if (square & movable_pieces(board, color)).is_empty() {
+    return Bitboard::new();
+  }
+  let king = (square & board.kings(color)).is_not_empty();
+  let capturing = capturing_pieces(board, color);
+  if capturing.is_empty() {
+    piece_moves(board, color, king, square)
+  } else if (square & capturing).is_not_empty() {
+    piece_captures(board, color, king, square)
+  } else {
+    Bitboard::new()
+  }
+}
+
+/// A complete move: the starting square followed by every square the piece lands on. Simple moves
+/// have a path of two squares; multi-jumps list each intermediate landing square.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Move {
+  path: Vec<Bitboard>,
+}
+
+impl Move {
+  pub fn new(path: Vec<Bitboard>) -> Move {
+    Move { path }
+  }
+
+  pub fn path(&self) -> &[Bitboard] {
+    &self.path
+  }
+
+  pub fn start(&self) -> Bitboard {
+    self.path.first().copied().unwrap_or_default()
+  }
+
+  pub fn end(&self) -> Bitboard {
+    self.path.last().copied().unwrap_or_default()
+  }
+
+  pub fn is_capture(&self) -> bool {
+    match self.path.as_slice() {
+      [start, end, ..] => (start.index() - end.index()).abs() > 9,
+      _ => false,
+    }
+  }
+
+  /// Iterates over the individual `(start, end)` steps of the move.
+  pub fn steps(&self) -> impl Iterator<Item = (Bitboard, Bitboard)> + '_ {
+    self.path.windows(2).map(|w| (w[0], w[1]))
+  }
+
+  /**
+  Checks whether this move is compatible with a possibly abbreviated `written` path: the start and
+  end squares must agree, and any intermediate squares given must appear in order.
+   */
+  pub fn matches(&self, written: &Move) -> bool {
+    if written.path.len() < 2 || self.start() != written.start() || self.end() != written.end() {
+      return false;
+    }
+    let mut remaining = self.path[1..self.path.len() - 1].iter();
+    written.path[1..written.path.len() - 1].iter()
+        .all(|square| remaining.any(|s| s == square))
+  }
+}
+
+/**
+Generates every legal move for `color`. Captures are mandatory, so when any are available only
+capture sequences are returned, each followed to the end of the jump chain. While a capture sequence
+is pending, only its continuations are legal; their paths start from the square the piece is on.
+ */
+pub fn legal_moves(board: &Checkerboard, color: PlayerColor) -> Vec<Move> {
+  let mut result = Vec::new();
+  if board.pending_capture().is_some() {
+    for piece in movable_pieces(board, color).squares() {
+      let mut path = vec![piece];
+      capture_sequences(board, color, &mut path, &mut result);
+    }
+  } else if color_captures(board, color).is_not_empty() {
+    for piece in board.pieces(color).squares() {
+      let mut path = vec![piece];
+      capture_sequences(board, color, &mut path, &mut result);
+    }
+  } else {
+    for piece in board.pieces(color).squares() {
+      let king = (piece & board.kings(color)).is_not_empty();
+      for target in piece_moves(board, color, king, piece).squares() {
+        result.push(Move::new(vec![piece, target]));
+      }
+    }
+  }
+  result
+}
+
+/**
+Finds the unique legal move matching a possibly abbreviated written move. If there is none, the
+written move is played out on a copy of the board to explain what is wrong with it; one that plays
+out cleanly stops short of a capture sequence that has to go on from where it ends.
+ */
+pub fn resolve_move(board: &Checkerboard, color: PlayerColor, written: &Move) -> Result<Move, MoveError> {
+  let mut candidates = legal_moves(board, color).into_iter()
+      .filter(|legal| legal.matches(written));
+  match (candidates.next(), candidates.next()) {
+    (Some(legal), None) => Ok(legal),
+    (Some(_), Some(_)) => Err(MoveError::Ambiguous),
+    (None, _) => {
+      let mut copy = *board;
+      match copy.apply(color, written) {
+        MoveResult::Invalid(error) => Err(error),
+        MoveResult::Valid(_) => Err(MoveError::MustContinueCapture(written.end())),
+      }
+    }
+  }
+}
+
+fn capture_sequences(board: &Checkerboard, color: PlayerColor, path: &mut Vec<Bitboard>, result: &mut Vec<Move>) {
+  let square = *path.last().unwrap();
+  let king = (square & board.kings(color)).is_not_empty();
+  for target in piece_captures(board, color, king, square).squares() {
+    let mut next = *board;
+    path.push(target);
+    match next.make_move(color, square, target) {
+      MoveResult::Valid(next_color) if next_color == color => capture_sequences(&next, color, path, result),
+      _ => result.push(Move::new(path.clone())),
+    }
+    path.pop();
+  }
+}
+
+/// Counts the leaf nodes of the legal move tree to `depth` plies. Each multi-jump counts as one move.
+pub fn perft(board: &Checkerboard, color: PlayerColor, depth: u32) -> u64 {
+  if depth == 0 {
+    return 1;
+  }
+  let moves = legal_moves(board, color);
+  if depth == 1 {
+    return moves.len() as u64;
+  }
+  moves.iter()
+      .map(|mv| {
+        let mut next = *board;
+        next.apply(color, mv);
+        perft(&next, !color, depth - 1)
+      })
+      .sum()
+}
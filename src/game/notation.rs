@@ -0,0 +1,199 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::game::{Checkerboard, PlayerColor};
+use crate::game::bitboard::Bitboard;
+use crate::game::moves::Move;
+
+/*  Standard numbering of the playable squares
+        01  02  03  04
+      05  06  07  08
+        09  10  11  12
+      13  14  15  16
+        17  18  19  20
+      21  22  23  24
+        25  26  27  28
+      29  30  31  32
+    White starts on 21-32, Black on 1-12. White moves first in games played here, while published
+    PDN games start with Black.
+ */
+
+/// Converts a single-square bitboard to its standard square number (1-32), or `None` if the square
+/// is not a single playable square.
+pub fn square_number(square: Bitboard) -> Option<u8> {
+  let index = square.index();
+  if index < 0 {
+    return None;
+  }
+  let rank = index >> 3;
+  let file = index & 7;
+  if (rank ^ file) & 1 != 0 {
+    return None;
+  }
+  Some(((7 - rank) * 4 + (file >> 1) + 1) as u8)
+}
+
+/// Converts a standard square number (1-32) to a single-square bitboard.
+pub fn number_square(number: u8) -> Option<Bitboard> {
+  if !(1..=32).contains(&number) {
+    return None;
+  }
+  let k = (number - 1) as i32;
+  let rank = 7 - (k >> 2);
+  let file = ((k & 3) << 1) + (rank & 1);
+  Some(Bitboard::from(1 << (rank * 8 + file)))
+}
+
+/// Lists the standard numbers of the squares in `squares`, e.g. `9, 14 and 23`.
+pub fn format_squares(squares: Bitboard) -> String {
+  let mut numbers: Vec<u8> = squares.squares().filter_map(square_number).collect();
+  numbers.sort_unstable();
+  let numbers: Vec<String> = numbers.iter().map(u8::to_string).collect();
+  match numbers.split_last() {
+    Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+    Some((last, _)) => last.clone(),
+    None => String::from("none"),
+  }
+}
+
+/// The side to move first in a PDN game without a `FEN` tag, as in published English draughts games.
+pub const PDN_FIRST_MOVER: PlayerColor = PlayerColor::Black;
+
+/// The FEN of the standard starting position as played here, with White to move.
+pub const START_FEN: &str = "W:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12";
+
+/**
+Parses a position in PDN FEN notation, e.g. `W:W21,22,K30:B1,2,3`. The first field is the side to
+move; each piece list starts with its color, kings are prefixed with `K`, and ranges such as `1-12`
+are accepted.
+ */
+pub fn parse_fen(fen: &str) -> Result<(Checkerboard, PlayerColor), NotationError> {
+  let bad_fen = || NotationError::BadFen(fen.to_string());
+  let trimmed = fen.trim().trim_matches('"').trim_end_matches('.');
+  let mut fields = trimmed.split(':').map(str::trim);
+  let color = parse_color(fields.next().ok_or_else(bad_fen)?).ok_or_else(bad_fen)?;
+
+  let mut pieces = [Bitboard::new(); 4];
+  for field in fields {
+    let mut chars = field.chars();
+    let side = chars.next().and_then(|c| parse_color(&c.to_string())).ok_or_else(bad_fen)?;
+    for entry in chars.as_str().split(',').map(str::trim).filter(|e| !e.is_empty()) {
+      let (king, entry) = match entry.strip_prefix('K') {
+        Some(rest) => (true, rest),
+        None => (false, entry),
+      };
+      let (first, last) = match entry.split_once('-') {
+        Some((first, last)) => (first, last),
+        None => (entry, entry),
+      };
+      let first = parse_square_number(first)?;
+      let last = parse_square_number(last)?;
+      for number in first..=last {
+        let square = number_square(number).ok_or_else(bad_fen)?;
+        if pieces.iter().any(|bb| (*bb & square).is_not_empty()) {
+          return Err(bad_fen());
+        }
+        pieces[Checkerboard::index(side, king)] |= square;
+      }
+    }
+  }
+  Ok((Checkerboard::from_pieces(pieces[0], pieces[1], pieces[2], pieces[3]), color))
+}
+
+/// Writes a position in PDN FEN notation.
+pub fn to_fen(board: &Checkerboard, color: PlayerColor) -> String {
+  let list = |side: PlayerColor| {
+    let mut squares: Vec<(u8, bool)> = board.men(side).squares().map(|sq| (sq, false))
+        .chain(board.kings(side).squares().map(|sq| (sq, true)))
+        .filter_map(|(sq, king)| square_number(sq).map(|n| (n, king)))
+        .collect();
+    squares.sort_unstable();
+    squares.iter()
+        .map(|(n, king)| if *king { format!("K{}", n) } else { n.to_string() })
+        .collect::<Vec<_>>()
+        .join(",")
+  };
+  format!("{}:W{}:B{}", color_letter(color), list(PlayerColor::White), list(PlayerColor::Black))
+}
+
+fn parse_color(s: &str) -> Option<PlayerColor> {
+  match s {
+    "W" | "w" => Some(PlayerColor::White),
+    "B" | "b" => Some(PlayerColor::Black),
+    _ => None,
+  }
+}
+
+fn color_letter(color: PlayerColor) -> char {
+  match color {
+    PlayerColor::White => 'W',
+    PlayerColor::Black => 'B',
+  }
+}
+
+fn parse_square_number(s: &str) -> Result<u8, NotationError> {
+  s.trim().parse::<u8>().ok()
+      .filter(|n| (1..=32).contains(n))
+      .ok_or_else(|| NotationError::BadSquare(s.trim().to_string()))
+}
+
+impl Display for Move {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let separator = if self.is_capture() { "x" } else { "-" };
+    let mut first = true;
+    for square in self.path() {
+      if !first {
+        f.write_str(separator)?;
+      }
+      first = false;
+      match square_number(*square) {
+        Some(number) => write!(f, "{}", number)?,
+        None => f.write_str("?")?,
+      }
+    }
+    Ok(())
+  }
+}
+
+impl FromStr for Move {
+  type Err = NotationError;
+
+  /// Parses a move such as `11-15`, `22x15` or `15x22x31`.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.trim();
+    let capture = s.contains(['x', 'X', ':']);
+    let parts: Vec<&str> = if capture {
+      s.split(['x', 'X', ':']).collect()
+    } else {
+      s.split('-').collect()
+    };
+    if parts.len() < 2 || !capture && parts.len() != 2 {
+      return Err(NotationError::BadMove(s.to_string()));
+    }
+    let path = parts.iter()
+        .map(|part| parse_square_number(part).map(|n| number_square(n).unwrap()))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Move::new(path))
+  }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum NotationError {
+  BadSquare(String),
+  BadMove(String),
+  BadFen(String),
+  BadPdn(String),
+}
+
+impl Display for NotationError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      NotationError::BadSquare(square) => write!(f, "'{}' is not a square number between 1 and 32", square),
+      NotationError::BadMove(mv) => write!(f, "'{}' is not a move; write moves like 11-15 or 22x15", mv),
+      NotationError::BadFen(fen) => write!(f, "'{}' is not a valid FEN position", fen),
+      NotationError::BadPdn(reason) => write!(f, "invalid PDN: {}", reason),
+    }
+  }
+}
+
+impl std::error::Error for NotationError {}
@@ -0,0 +1,187 @@
+use std::fmt::{Display, Formatter};
+
+use crate::game::{Checkerboard, PlayerColor};
+use crate::game::moves::{resolve_move, Move};
+use crate::game::notation::{NotationError, parse_fen, PDN_FIRST_MOVER};
+
+const RESULTS: [&str; 7] = ["1-0", "0-1", "1/2-1/2", "2-0", "0-2", "1-1", "*"];
+
+/// A single game in Portable Draughts Notation: its tag pairs, moves and result.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PdnGame {
+  pub tags: Vec<(String, String)>,
+  pub moves: Vec<Move>,
+  /// Annotations of the moves with the same index; written out but not read back by `parse_pdn`.
+  pub annotations: Vec<Annotation>,
+  pub result: String,
+}
+
+/// Remarks on a move: a numeric annotation glyph such as 4 for `$4` (a blunder) and a comment.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Annotation {
+  pub nag: Option<u8>,
+  pub comment: Option<String>,
+}
+
+impl PdnGame {
+  pub fn new() -> PdnGame {
+    PdnGame { tags: Vec::new(), moves: Vec::new(), annotations: Vec::new(), result: String::from("*") }
+  }
+
+  pub fn tag(&self, name: &str) -> Option<&str> {
+    self.tags.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+  }
+
+  pub fn set_tag(&mut self, name: &str, value: impl Into<String>) {
+    let value = value.into();
+    match self.tags.iter_mut().find(|(key, _)| key == name) {
+      Some(tag) => tag.1 = value,
+      None => self.tags.push((name.to_string(), value)),
+    }
+  }
+
+  /// The position the game starts from: the `FEN` tag if present, otherwise the standard start with
+  /// `PDN_FIRST_MOVER` to move.
+  pub fn start_position(&self) -> Result<(Checkerboard, PlayerColor), NotationError> {
+    match self.tag("FEN") {
+      Some(fen) => parse_fen(fen),
+      None => Ok((Checkerboard::new(), PDN_FIRST_MOVER)),
+    }
+  }
+
+  /**
+  Replays the moves from the start position and returns the final position. Moves may abbreviate
+  multi-jumps as long as they are unambiguous.
+   */
+  pub fn final_position(&self) -> Result<(Checkerboard, PlayerColor), NotationError> {
+    let (mut board, mut color) = self.start_position()?;
+    for mv in &self.moves {
+      let legal = resolve_move(&board, color, mv)
+          .map_err(|e| NotationError::BadPdn(format!("illegal move {}: {}", mv, e)))?;
+      board.apply(color, &legal);
+      color = !color;
+    }
+    Ok((board, color))
+  }
+}
+
+impl Display for PdnGame {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    for (key, value) in &self.tags {
+      writeln!(f, "[{} \"{}\"]", key, value.replace('\\', "\\\\").replace('"', "\\\""))?;
+    }
+    writeln!(f)?;
+
+    let first = self.start_position().map(|(_, color)| color).unwrap_or(PDN_FIRST_MOVER);
+    let mut tokens = Vec::new();
+    let mut number = 1;
+    let mut color = first;
+    for (i, mv) in self.moves.iter().enumerate() {
+      if color == first {
+        tokens.push(format!("{}.", number));
+      } else if i == 0 {
+        tokens.push(format!("{}...", number));
+      }
+      tokens.push(mv.to_string());
+      if let Some(annotation) = self.annotations.get(i) {
+        if let Some(nag) = annotation.nag {
+          tokens.push(format!("${}", nag));
+        }
+        if let Some(comment) = &annotation.comment {
+          tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+        }
+      }
+      if color != first {
+        number += 1;
+      }
+      color = !color;
+    }
+    tokens.push(self.result.clone());
+
+    let mut line = String::new();
+    for token in tokens {
+      if !line.is_empty() && line.len() + token.len() + 1 > 79 {
+        writeln!(f, "{}", line)?;
+        line.clear();
+      }
+      if !line.is_empty() {
+        line.push(' ');
+      }
+      line.push_str(&token);
+    }
+    writeln!(f, "{}", line)
+  }
+}
+
+/**
+Parses every game in a PDN document. Comments, variations, NAGs and move-strength annotations are
+skipped; each game ends at its result token.
+ */
+pub fn parse_pdn(text: &str) -> Result<Vec<PdnGame>, NotationError> {
+  let mut games = Vec::new();
+  let mut game = PdnGame::new();
+  let mut started = false;
+  let mut chars = text.chars().peekable();
+
+  while let Some(&c) = chars.peek() {
+    match c {
+      '[' => {
+        chars.next();
+        let tag: String = chars.by_ref().take_while(|&c| c != ']').collect();
+        let (key, value) = tag.trim().split_once(char::is_whitespace)
+            .ok_or_else(|| NotationError::BadPdn(format!("malformed tag [{}]", tag)))?;
+        let value = value.trim().trim_matches('"').replace("\\\"", "\"").replace("\\\\", "\\");
+        game.tags.push((key.to_string(), value));
+        started = true;
+      }
+      '{' => {
+        chars.next();
+        chars.by_ref().take_while(|&c| c != '}').for_each(drop);
+      }
+      '(' => {
+        let mut depth = 0;
+        for c in chars.by_ref() {
+          match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+          }
+          if depth == 0 {
+            break;
+          }
+        }
+      }
+      c if c.is_whitespace() => {
+        chars.next();
+      }
+      _ => {
+        let mut token = String::new();
+        while let Some(&c) = chars.peek() {
+          if c.is_whitespace() || "[{(".contains(c) {
+            break;
+          }
+          token.push(c);
+          chars.next();
+        }
+        if RESULTS.contains(&token.as_str()) {
+          game.result = token;
+          games.push(std::mem::replace(&mut game, PdnGame::new()));
+          started = false;
+          continue;
+        }
+        let token = token.trim_start_matches(|c: char| c.is_ascii_digit() && token.contains('.'))
+            .trim_start_matches('.')
+            .trim_end_matches(['!', '?']);
+        if token.is_empty() || token.starts_with('$') {
+          continue;
+        }
+        game.moves.push(token.parse()?);
+        started = true;
+      }
+    }
+  }
+  if started {
+    games.push(game);
+  }
+  Ok(games)
+}
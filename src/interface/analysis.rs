@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+use cursive::{Printer, Vec2, View};
+use cursive::theme::ColorStyle;
+
+use crate::engine::search::{format_score, SearchInfo, Searcher};
+use crate::game::{Checkerboard, PlayerColor};
+
+const WIDTH: usize = 30;
+const HEIGHT: usize = 35;
+
+/**
+A search of one position running in a background thread until it is dropped, keeping the best few
+lines of the deepest iteration finished so far.
+ */
+pub struct Analysis {
+  position: (Checkerboard, PlayerColor),
+  receiver: Receiver<Vec<SearchInfo>>,
+  stop: Arc<AtomicBool>,
+  lines: Vec<SearchInfo>,
+}
+
+impl Analysis {
+  /// Starts `searcher` searching `board` for the best `count` moves of `color`; its limits should allow an endless search.
+  pub fn start(board: Checkerboard, color: PlayerColor, count: usize, mut searcher: Searcher) -> Analysis {
+    let (sender, receiver) = channel();
+    let stop = searcher.stop_flag();
+    thread::spawn(move || {
+      searcher.search_lines(&board, color, count, |lines| {
+        let _ = sender.send(lines.to_vec());
+      });
+    });
+    Analysis { position: (board, color), receiver, stop, lines: Vec::new() }
+  }
+
+  pub fn position(&self) -> (Checkerboard, PlayerColor) {
+    self.position
+  }
+
+  /// Picks up the latest finished iteration; returns whether the lines changed.
+  pub fn poll(&mut self) -> bool {
+    match self.receiver.try_iter().last() {
+      Some(lines) => {
+        self.lines = lines;
+        true
+      }
+      None => false,
+    }
+  }
+
+  pub fn lines(&self) -> &[SearchInfo] {
+    &self.lines
+  }
+}
+
+impl Drop for Analysis {
+  fn drop(&mut self) {
+    self.stop.store(true, Ordering::Relaxed);
+  }
+}
+
+/**
+Side panel showing the analysis of the position on the board: the search depth, then each
+candidate move with its score from White's point of view and its principal variation. Like the
+move list it never takes focus and is updated by the board view.
+ */
+pub struct AnalysisView {
+  rows: Vec<(String, bool)>,
+}
+
+impl AnalysisView {
+  pub fn new() -> AnalysisView {
+    AnalysisView { rows: vec![(String::from("Searching..."), false)] }
+  }
+
+  /// Shows `lines` found for `color`, the side to move; the candidate moves are highlighted.
+  pub fn set_lines(&mut self, color: PlayerColor, lines: &[SearchInfo]) {
+    self.rows.clear();
+    let first = match lines.first() {
+      Some(first) => first,
+      None => {
+        self.rows.push((String::from("Searching..."), false));
+        return;
+      }
+    };
+    self.rows.push((format!("Depth {}, {} nodes", first.depth, first.nodes), false));
+    for (i, line) in lines.iter().enumerate() {
+      let score = if color == PlayerColor::White { line.score } else { -line.score };
+      let mut moves = line.pv.iter().map(ToString::to_string);
+      let best = moves.next().unwrap_or_default();
+      self.rows.push((String::new(), false));
+      self.rows.push((format!("{}. {:<8} {:>7}", i + 1, best, format_score(score)), true));
+      let mut row = String::from("  ");
+      for mv in moves {
+        if row.len() + mv.len() + 1 > WIDTH {
+          self.rows.push((std::mem::replace(&mut row, String::from("  ")), false));
+        }
+        row.push(' ');
+        row.push_str(&mv);
+      }
+      if !row.trim().is_empty() {
+        self.rows.push((row, false));
+      }
+    }
+  }
+
+  /// Shows a note instead of search results, e.g. for a position without legal moves.
+  pub fn set_note(&mut self, note: &str) {
+    self.rows = vec![(note.to_string(), false)];
+  }
+}
+
+impl Default for AnalysisView {
+  fn default() -> Self {
+    AnalysisView::new()
+  }
+}
+
+impl View for AnalysisView {
+  fn draw(&self, printer: &Printer) {
+    for (y, (text, candidate)) in self.rows.iter().enumerate().take(printer.size.y) {
+      let style = if *candidate { ColorStyle::title_primary() } else { ColorStyle::primary() };
+      printer.with_color(style, |printer| printer.print((0, y), text));
+    }
+  }
+
+  fn required_size(&mut self, _: Vec2) -> Vec2 {
+    Vec2::new(WIDTH, HEIGHT)
+  }
+}
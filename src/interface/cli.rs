@@ -0,0 +1,63 @@
+use std::io::{BufRead, Write};
+
+use eyre::Result;
+
+use crate::game::MoveResult;
+use crate::game::moves::Move;
+use crate::interface::CheckersGame;
+
+const CLI_HELP: &str = "\
+Enter moves in standard notation, e.g. 22-18 or 15x22x29 (intermediate jump squares may be omitted
+when unambiguous). Other commands:
+  board   print the board
+  moves   list the legal moves
+  help    show this message
+  quit    end the game";
+
+/// Runs a line-based game on stdin and stdout.
+pub fn run_cli(game: CheckersGame) -> Result<()> {
+  let stdin = std::io::stdin();
+  let stdout = std::io::stdout();
+  play(game, stdin.lock(), stdout.lock())
+}
+
+/**
+Plays a game reading one command per line from `input` and writing the board and messages to
+`output`. Returns when the game ends, on `quit`, or at the end of the input.
+ */
+pub fn play<R: BufRead, W: Write>(mut game: CheckersGame, input: R, mut output: W) -> Result<()> {
+  write!(output, "{}", game.board())?;
+  writeln!(output, "{} to move", game.on_move())?;
+  output.flush()?;
+
+  for line in input.lines() {
+    let line = line?;
+    let command = line.trim();
+    match command {
+      "" => continue,
+      "quit" | "exit" => break,
+      "help" | "?" => writeln!(output, "{}", CLI_HELP)?,
+      "board" => write!(output, "{}", game.board())?,
+      "moves" => {
+        let moves: Vec<String> = game.legal_moves().iter().map(Move::to_string).collect();
+        writeln!(output, "{}", moves.join(" "))?;
+      }
+      _ => match command.parse::<Move>() {
+        Ok(mv) => match game.play_move(&mv) {
+          MoveResult::Valid(_) => write!(output, "{}", game.board())?,
+          MoveResult::Invalid(error) => writeln!(output, "Invalid move {}: {}", command, error)?,
+        },
+        Err(e) => writeln!(output, "Error: {}", e)?,
+      }
+    }
+
+    if let Some(result) = game.result() {
+      writeln!(output, "{}", result)?;
+      break;
+    }
+    writeln!(output, "{} to move", game.on_move())?;
+    output.flush()?;
+  }
+  output.flush()?;
+  Ok(())
+}
@@ -0,0 +1,119 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::game::PlayerColor;
+
+/// Initial thinking time per player plus the increment added after each move.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TimeControl {
+  pub initial: Duration,
+  pub increment: Duration,
+}
+
+impl FromStr for TimeControl {
+  type Err = String;
+
+  /// Parses `minutes` or `minutes+increment_seconds`, e.g. `5` or `3+2`.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let error = || format!("'{}' is not a time control; use minutes[+increment seconds], e.g. 5+3", s);
+    let (minutes, increment) = match s.split_once('+') {
+      Some((minutes, increment)) => (minutes, increment),
+      None => (s, "0"),
+    };
+    let minutes: f64 = minutes.trim().parse().map_err(|_| error())?;
+    let increment: f64 = increment.trim().parse().map_err(|_| error())?;
+    if minutes <= 0.0 || increment < 0.0 {
+      return Err(error());
+    }
+    Ok(TimeControl {
+      initial: Duration::from_secs_f64(minutes * 60.0),
+      increment: Duration::from_secs_f64(increment),
+    })
+  }
+}
+
+impl Display for TimeControl {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}+{}", self.initial.as_secs_f64() / 60.0, self.increment.as_secs_f64())
+  }
+}
+
+/// A two-sided game clock. Only the side to move has its time running.
+#[derive(Clone, Debug)]
+pub struct Clock {
+  control: TimeControl,
+  remaining: [Duration; 2],
+  running: Option<(PlayerColor, Instant)>,
+}
+
+impl Clock {
+  pub fn new(control: TimeControl) -> Clock {
+    Clock { control, remaining: [control.initial; 2], running: None }
+  }
+
+  pub fn control(&self) -> TimeControl {
+    self.control
+  }
+
+  pub fn remaining(&self, color: PlayerColor) -> Duration {
+    let stored = self.remaining[Clock::slot(color)];
+    match self.running {
+      Some((running, since)) if running == color => stored.saturating_sub(since.elapsed()),
+      _ => stored,
+    }
+  }
+
+  pub fn set_remaining(&mut self, color: PlayerColor, remaining: Duration) {
+    self.remaining[Clock::slot(color)] = remaining;
+  }
+
+  pub fn running(&self) -> Option<PlayerColor> {
+    self.running.map(|(color, _)| color)
+  }
+
+  pub fn start(&mut self, color: PlayerColor) {
+    self.stop();
+    self.running = Some((color, Instant::now()));
+  }
+
+  /// Stops the running side's time without adding the increment.
+  pub fn stop(&mut self) {
+    if let Some((color, _)) = self.running {
+      self.remaining[Clock::slot(color)] = self.remaining(color);
+      self.running = None;
+    }
+  }
+
+  /// Ends the running side's turn: stops its time, adds the increment and starts the opponent.
+  pub fn press(&mut self) {
+    if let Some((color, _)) = self.running {
+      self.stop();
+      self.remaining[Clock::slot(color)] += self.control.increment;
+      self.start(!color);
+    }
+  }
+
+  /// The side whose time has run out, if any.
+  pub fn flagged(&self) -> Option<PlayerColor> {
+    [PlayerColor::White, PlayerColor::Black].into_iter()
+        .find(|&color| self.remaining(color).is_zero())
+  }
+
+  fn slot(color: PlayerColor) -> usize {
+    match color {
+      PlayerColor::White => 0,
+      PlayerColor::Black => 1,
+    }
+  }
+}
+
+/// Formats a remaining time as `m:ss`, with tenths under ten seconds.
+pub fn format_time(time: Duration) -> String {
+  let secs = time.as_secs();
+  if secs < 10 {
+    format!("0:{:02}.{}", secs, time.subsec_millis() / 100)
+  } else {
+    format!("{}:{:02}", secs / 60, secs % 60)
+  }
+}
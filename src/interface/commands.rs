@@ -0,0 +1,493 @@
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Instant;
+
+use eyre::{eyre, Result, WrapErr};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::engine::difficulty::Difficulty;
+use crate::engine::mcts::{Mcts, MctsLimits, Playout};
+use crate::engine::network::Network;
+use crate::engine::review::review_game;
+use crate::engine::search::{format_score, SearchLimits, Searcher};
+use crate::engine::training::{DataFormat, load_samples, play_training_game, SelfPlayOptions, train, TrainingOptions};
+use crate::game::{Checkerboard, PlayerColor};
+use crate::game::moves::{legal_moves, perft, Move};
+use crate::game::notation::{number_square, parse_fen, square_number, to_fen};
+use crate::game::pdn::{parse_pdn, PdnGame};
+use crate::interface::{CheckersGame, GameResult};
+use crate::interface::protocol::info_line;
+use crate::interface::puzzles::{extract_puzzles, write_puzzles, Puzzle};
+
+/// Reads every game from a PDN file.
+pub fn load_games(path: &Path) -> Result<Vec<PdnGame>> {
+  let text = fs::read_to_string(path).wrap_err_with(|| format!("could not read {}", path.display()))?;
+  parse_pdn(&text).wrap_err_with(|| format!("could not parse {}", path.display()))
+}
+
+/**
+Interprets `input` as a PDN file if such a file exists, otherwise as a FEN string, and returns the
+position reached (the end of the first game for PDN).
+ */
+pub fn load_position(input: &str) -> Result<(Checkerboard, PlayerColor)> {
+  let path = Path::new(input);
+  if path.is_file() {
+    let games = load_games(path)?;
+    let game = games.first().ok_or_else(|| eyre!("{} contains no games", input))?;
+    Ok(game.final_position()?)
+  } else {
+    Ok(parse_fen(input)?)
+  }
+}
+
+/// Prints the perft count for every depth up to `depth`, with per-move counts if `divide` is set.
+pub fn perft_report<W: Write>(board: &Checkerboard, color: PlayerColor, depth: u32, divide: bool, output: &mut W) -> Result<()> {
+  if divide && depth > 0 {
+    for mv in legal_moves(board, color) {
+      let mut next = *board;
+      next.apply(color, &mv);
+      writeln!(output, "{:>10} {}", mv.to_string(), perft(&next, !color, depth - 1))?;
+    }
+  }
+  for d in 1..=depth {
+    let start = Instant::now();
+    let nodes = perft(board, color, d);
+    writeln!(output, "perft({}) = {} ({} ms)", d, nodes, start.elapsed().as_millis())?;
+  }
+  Ok(())
+}
+
+/// Searches a position with `searcher` and prints each iteration followed by the best move.
+pub fn analyze<W: Write>(board: &Checkerboard, color: PlayerColor, searcher: &mut Searcher, output: &mut W) -> Result<()> {
+  write!(output, "{}", board)?;
+  writeln!(output, "{}", to_fen(board, color))?;
+  let mut lines = Vec::new();
+  let info = searcher.search_with(board, color, |info| lines.push(info_line(info)));
+  for line in lines {
+    writeln!(output, "{}", line)?;
+  }
+  match info.best_move() {
+    Some(mv) => writeln!(output, "{} to move: best {} ({})", color, mv, format_score(info.score))?,
+    None => writeln!(output, "{} to move has no legal moves", color)?,
+  }
+  Ok(())
+}
+
+/**
+Reviews every game in a PDN file with `searcher`, marking inaccuracies, mistakes and blunders with glyphs and
+comments. The annotated games are written to `annotated` if given, with a summary of each player's
+accuracy per game going to `output`; otherwise the annotated games themselves go to `output`.
+ */
+pub fn review_games<W: Write>(path: &Path, searcher: &mut Searcher, annotated: Option<&Path>, output: &mut W) -> Result<()> {
+  let mut pdn_text = String::new();
+  for (i, pdn) in load_games(path)?.into_iter().enumerate() {
+    let game = CheckersGame::from_pdn(&pdn).wrap_err_with(|| format!("game {} of {}", i + 1, path.display()))?;
+    let review = review_game(game.start_position(), game.history(), searcher);
+    let mut pdn = pdn;
+    pdn.moves = game.history().to_vec();
+    review.annotate(&mut pdn);
+    match annotated {
+      Some(_) => {
+        writeln!(output, "Game {}: {} - {}", i + 1, pdn.tag("White").unwrap_or("?"), pdn.tag("Black").unwrap_or("?"))?;
+        writeln!(output, "  {}", review.summary(PlayerColor::White))?;
+        writeln!(output, "  {}", review.summary(PlayerColor::Black))?;
+      }
+      None => writeln!(output, "{}", pdn)?,
+    }
+    pdn_text.push_str(&pdn.to_string());
+    pdn_text.push('\n');
+  }
+  if let Some(path) = annotated {
+    fs::write(path, pdn_text).wrap_err_with(|| format!("could not write {}", path.display()))?;
+  }
+  Ok(())
+}
+
+/**
+Searches every game in a PDN file with `searcher` for puzzles, positions with a unique winning shot whether the
+player found it or not. The puzzles are written to `puzzles` if given, with a line per puzzle
+going to `output`; otherwise the puzzle file itself goes to `output`. A position met in several
+games gives one puzzle.
+ */
+pub fn extract_game_puzzles<W: Write>(path: &Path, searcher: &mut Searcher, puzzles: Option<&Path>, output: &mut W) -> Result<()> {
+  let mut extracted: Vec<Puzzle> = Vec::new();
+  for (i, pdn) in load_games(path)?.into_iter().enumerate() {
+    let game = CheckersGame::from_pdn(&pdn).wrap_err_with(|| format!("game {} of {}", i + 1, path.display()))?;
+    let start = game.start_position();
+    for found in extract_puzzles(start, game.history(), searcher) {
+      if extracted.iter().any(|puzzle| puzzle.id() == found.puzzle.id()) {
+        continue;
+      }
+      if puzzles.is_some() {
+        let number = found.ply / 2 + 1;
+        let dots = if found.puzzle.color == start.1 { "." } else { "..." };
+        writeln!(output, "Game {}, {}{} {} {} {} ({}, rated {})", i + 1, number, dots, found.puzzle.color,
+                 if found.found { "found" } else { "missed" }, found.puzzle.solution[0], found.puzzle.theme,
+                 found.puzzle.rating)?;
+      }
+      extracted.push(found.puzzle);
+    }
+  }
+  let text = write_puzzles(&extracted)?;
+  match puzzles {
+    Some(path) => {
+      writeln!(output, "{} puzzles written to {}", extracted.len(), path.display())?;
+      fs::write(path, text).wrap_err_with(|| format!("could not write {}", path.display()))?;
+    }
+    None => write!(output, "{}", text)?,
+  }
+  Ok(())
+}
+
+/// Settings for `train_network`.
+#[derive(Clone, Debug)]
+pub struct TrainOptions {
+  pub games: u32,
+  pub self_play: SelfPlayOptions,
+  pub training: TrainingOptions,
+  /// Weights to start from, which also evaluate the self-play games; random weights if not set.
+  pub init: Option<PathBuf>,
+  /// Binary data files, as written by `generate_self_play`, to train on besides the games played.
+  pub data: Vec<PathBuf>,
+  pub output: PathBuf,
+  /// Makes the run repeatable.
+  pub seed: Option<u64>,
+}
+
+/// Where self-play games start.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OpeningChoice {
+  /// From the starting position, with the random moves of the game options.
+  Random,
+  /**
+  From every legal three-move opening, shuffled and dealt in turn. These are all the lines of three
+  plies, not the 156 openings of the official three-move ballot, so lopsided ones are included.
+   */
+  ThreeMove,
+  /**
+  From the openings in a PDN file, as read by `load_openings`, shuffled and dealt in turn. A file of
+  the three-move ballot gives games from the ballot openings only.
+   */
+  File(PathBuf),
+}
+
+/// Settings for `generate_self_play`.
+#[derive(Clone, Debug)]
+pub struct SelfPlayDataOptions {
+  pub games: u32,
+  /// Number of games played at once.
+  pub threads: usize,
+  pub openings: OpeningChoice,
+  pub game: SelfPlayOptions,
+  pub format: DataFormat,
+  pub path: PathBuf,
+  /// Makes the run repeatable, whatever the number of threads.
+  pub seed: Option<u64>,
+}
+
+/// What `generate_self_play` wrote.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct SelfPlayTotals {
+  pub games: u32,
+  pub positions: u64,
+  pub white_wins: u32,
+  pub black_wins: u32,
+  pub draws: u32,
+}
+
+/**
+Plays self-play games on several threads and writes their positions, labeled with the search
+score and the result, to a data file, reporting progress to `output`. Each game has its own
+random generator seeded from the run's seed and the game number, so a seeded run writes the same
+games with any number of threads, though not in the same order.
+ */
+pub fn generate_self_play<W: Write>(options: &SelfPlayDataOptions, output: &mut W) -> Result<SelfPlayTotals> {
+  let seed = options.seed.unwrap_or_else(|| StdRng::from_entropy().gen());
+  let mut opening_lines = match &options.openings {
+    OpeningChoice::Random => Vec::new(),
+    OpeningChoice::ThreeMove => openings(3),
+    OpeningChoice::File(path) => load_openings(path)?,
+  };
+  opening_lines.shuffle(&mut StdRng::seed_from_u64(seed));
+  let file = fs::File::create(&options.path).wrap_err_with(|| format!("could not create {}", options.path.display()))?;
+  let mut file = BufWriter::new(file);
+  file.write_all(&options.format.header())?;
+
+  let next_game = AtomicU32::new(0);
+  let (sender, receiver) = channel();
+  let mut totals = SelfPlayTotals::default();
+  thread::scope(|scope| -> Result<()> {
+    for _ in 0..options.threads.max(1) {
+      let sender = sender.clone();
+      let (next_game, opening_lines) = (&next_game, &opening_lines);
+      scope.spawn(move || loop {
+        let game = next_game.fetch_add(1, Ordering::Relaxed);
+        if game >= options.games {
+          break;
+        }
+        let mut rng = StdRng::seed_from_u64(seed ^ (game as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let opening = if opening_lines.is_empty() { &[][..] } else { &opening_lines[game as usize % opening_lines.len()][..] };
+        if sender.send(play_training_game(&options.game, opening, &mut rng)).is_err() {
+          break;
+        }
+      });
+    }
+    drop(sender);
+    for samples in receiver {
+      totals.games += 1;
+      totals.positions += samples.len() as u64;
+      match samples.first().map(|sample| sample.outcome) {
+        Some(1.0) => totals.white_wins += 1,
+        Some(0.0) => totals.black_wins += 1,
+        _ => totals.draws += 1,
+      }
+      for sample in &samples {
+        file.write_all(&sample.to_bytes(options.format))?;
+      }
+      if totals.games % 10 == 0 || totals.games == options.games {
+        writeln!(output, "{}/{} games played, {} positions", totals.games, options.games, totals.positions)?;
+      }
+    }
+    Ok(())
+  })?;
+  file.flush()?;
+  writeln!(output, "White won {}, Black won {}, {} drawn; positions written to {}",
+           totals.white_wins, totals.black_wins, totals.draws, options.path.display())?;
+  Ok(totals)
+}
+
+/**
+Trains an evaluation network: plays self-play games, labels their positions with shallow search
+scores and the results, trains on them and writes the weights, reporting progress to `output`.
+ */
+pub fn train_network<W: Write>(options: &TrainOptions, output: &mut W) -> Result<()> {
+  let mut rng = match options.seed {
+    Some(seed) => StdRng::seed_from_u64(seed),
+    None => StdRng::from_entropy(),
+  };
+  let mut network = match &options.init {
+    Some(path) => Network::load(path)?,
+    None => Network::random(rng.gen()),
+  };
+  let mut self_play = options.self_play.clone();
+  if options.init.is_some() {
+    self_play.network = Some(Arc::new(network.clone()));
+  }
+
+  let mut samples = Vec::new();
+  for path in &options.data {
+    samples.extend(load_samples(path)?);
+  }
+  if !options.data.is_empty() {
+    writeln!(output, "{} positions loaded", samples.len())?;
+  }
+  for game in 1..=options.games {
+    samples.extend(play_training_game(&self_play, &[], &mut rng));
+    if game % 10 == 0 || game == options.games {
+      writeln!(output, "{} games played, {} positions", game, samples.len())?;
+    }
+  }
+  let mut write_error = None;
+  train(&mut network, &samples, &options.training, &mut rng, |epoch, loss| {
+    if let Err(e) = writeln!(output, "Epoch {}: loss {:.5}", epoch, loss) {
+      write_error.get_or_insert(e);
+    }
+  });
+  if let Some(e) = write_error {
+    return Err(e.into());
+  }
+  network.save(&options.output)?;
+  writeln!(output, "Weights written to {}", options.output.display())?;
+  Ok(())
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ConvertFormat {
+  Fen,
+  Pdn,
+  Board,
+}
+
+/// Converts a PDN file or FEN string to another representation of the same game or position.
+pub fn convert<W: Write>(input: &str, format: ConvertFormat, output: &mut W) -> Result<()> {
+  let path = Path::new(input);
+  let games = if path.is_file() {
+    load_games(path)?
+  } else {
+    let (board, color) = parse_fen(input)?;
+    vec![CheckersGame::from_position(board, color).to_pdn()]
+  };
+  for pdn in games {
+    match format {
+      ConvertFormat::Pdn => {
+        let game = CheckersGame::from_pdn(&pdn)?;
+        let mut normalized = game.to_pdn();
+        normalized.tags = pdn.tags.clone();
+        normalized.result = pdn.result.clone();
+        writeln!(output, "{}", normalized)?;
+      }
+      ConvertFormat::Fen => {
+        let (board, color) = pdn.final_position()?;
+        writeln!(output, "{}", to_fen(&board, color))?;
+      }
+      ConvertFormat::Board => {
+        let (board, color) = pdn.final_position()?;
+        write!(output, "{}", board)?;
+        writeln!(output, "{} to move", color)?;
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Settings for an engine-versus-engine match.
+#[derive(Clone, Debug)]
+pub struct MatchOptions {
+  pub games: u32,
+  pub first: SearchLimits,
+  pub second: SearchLimits,
+  /// Settings for the second engine to use Monte Carlo tree search instead of `second`.
+  pub second_mcts: Option<(MctsLimits, Playout)>,
+  /// Makes the second engine play at this level instead of searching with `second`.
+  pub second_difficulty: Option<Difficulty>,
+  /// Games still running after this many plies are adjudicated as draws.
+  pub max_plies: u32,
+  pub pdn: Option<PathBuf>,
+}
+
+/// Wins, losses and draws from the first engine's point of view.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct MatchScore {
+  pub wins: u32,
+  pub losses: u32,
+  pub draws: u32,
+}
+
+/**
+Plays a match between two search settings. Each two-ply opening is played twice with colors
+swapped, so the engines meet in varied positions even though the search is deterministic.
+ */
+pub fn run_match<W: Write>(options: &MatchOptions, output: &mut W) -> Result<MatchScore> {
+  let openings = openings(2);
+  let mut score = MatchScore::default();
+  let mut pdn_text = String::new();
+
+  for game_number in 0..options.games {
+    let opening = &openings[(game_number / 2) as usize % openings.len()];
+    let first_color = if game_number % 2 == 0 { PlayerColor::White } else { PlayerColor::Black };
+    let mut game = CheckersGame::new();
+    for mv in opening {
+      game.play_move(mv);
+    }
+    let mut mcts = options.second_mcts.clone().map(|(limits, playout)| Mcts::new(limits, playout));
+    while game.result().is_none() {
+      if game.history().len() as u32 >= options.max_plies {
+        game.set_result(GameResult::Draw);
+        break;
+      }
+      let info = match (&mut mcts, options.second_difficulty) {
+        (Some(mcts), _) if game.on_move() != first_color => mcts.search(game.board(), game.on_move()),
+        (_, Some(difficulty)) if game.on_move() != first_color => {
+          let mut searcher = Searcher::new(SearchLimits::depth(difficulty.depth()));
+          difficulty.choose_move(game.board(), game.on_move(), &mut searcher, &mut rand::thread_rng())
+        }
+        _ => {
+          let limits = if game.on_move() == first_color { &options.first } else { &options.second };
+          Searcher::new(limits.clone()).search(game.board(), game.on_move())
+        }
+      };
+      match info.best_move() {
+        Some(mv) => {
+          let mv = mv.clone();
+          game.play_move(&mv);
+        }
+        None => break,
+      }
+    }
+
+    let result = game.result().unwrap_or(GameResult::Draw);
+    match result {
+      GameResult::Victory(color) if color == first_color => score.wins += 1,
+      GameResult::Victory(_) => score.losses += 1,
+      GameResult::Draw => score.draws += 1,
+    }
+    writeln!(output, "Game {}: first engine as {}, {} after {} plies",
+             game_number + 1, first_color, GameResult::pdn_result(Some(result)), game.history().len())?;
+
+    let mut pdn = game.to_pdn();
+    pdn.set_tag("Event", "Engine match");
+    pdn.set_tag("Round", (game_number + 1).to_string());
+    pdn.set_tag(&first_color.to_string(), "First engine");
+    pdn.set_tag(&(!first_color).to_string(), "Second engine");
+    pdn_text.push_str(&pdn.to_string());
+    pdn_text.push('\n');
+  }
+
+  writeln!(output, "Score for the first engine: +{} -{} ={}", score.wins, score.losses, score.draws)?;
+  if let Some(path) = &options.pdn {
+    fs::write(path, pdn_text).wrap_err_with(|| format!("could not write {}", path.display()))?;
+  }
+  Ok(score)
+}
+
+/// Reads the openings in a PDN file; see `opening_lines`.
+pub fn load_openings(path: &Path) -> Result<Vec<Vec<Move>>> {
+  opening_lines(&load_games(path)?).wrap_err_with(|| format!("invalid openings in {}", path.display()))
+}
+
+/**
+The moves of each game, as openings for self-play. The games must start from the initial position.
+Published openings have Black moving first; they are turned round to this program's numbering, in
+which White moves first, by swapping the colors and numbering square `n` as `33 - n`. Openings
+that appear more than once are kept once.
+ */
+pub fn opening_lines(games: &[PdnGame]) -> Result<Vec<Vec<Move>>> {
+  let mut lines = Vec::new();
+  for (i, pdn) in games.iter().enumerate() {
+    let game = CheckersGame::from_pdn(pdn).wrap_err_with(|| format!("opening {}", i + 1))?;
+    let line = match game.start_position() {
+      (board, PlayerColor::White) if board == Checkerboard::new() => game.history().to_vec(),
+      (board, PlayerColor::Black) if board == Checkerboard::new() => game.history().iter().map(turn_round).collect(),
+      _ => return Err(eyre!("opening {} does not start from the initial position", i + 1)),
+    };
+    if line.is_empty() {
+      return Err(eyre!("opening {} has no moves", i + 1));
+    }
+    if !lines.contains(&line) {
+      lines.push(line);
+    }
+  }
+  Ok(lines)
+}
+
+/// The move as seen from the other side of the board.
+fn turn_round(mv: &Move) -> Move {
+  Move::new(mv.path().iter().filter_map(|&square| square_number(square).and_then(|n| number_square(33 - n))).collect())
+}
+
+/// Every sequence of `plies` legal moves from the starting position.
+pub fn openings(plies: u32) -> Vec<Vec<Move>> {
+  let mut lines = vec![(Checkerboard::new(), Vec::new())];
+  for ply in 0..plies {
+    let color = if ply % 2 == 0 { PlayerColor::White } else { PlayerColor::Black };
+    let mut longer = Vec::new();
+    for (board, moves) in lines {
+      for mv in legal_moves(&board, color) {
+        let mut next = board;
+        next.apply(color, &mv);
+        let mut moves: Vec<Move> = moves.clone();
+        moves.push(mv);
+        longer.push((next, moves));
+      }
+    }
+    lines = longer;
+  }
+  lines.into_iter().map(|(_, moves)| moves).collect()
+}
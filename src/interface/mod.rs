@@ -1,22 +1,256 @@
-use cursive::Cursive;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cursive::event::Event;
 use cursive::traits::*;
-use cursive::views::Dialog;
-use crate::game::{Checkerboard, PlayerColor};
+use cursive::views::{Dialog, LinearLayout, Panel};
+use crate::engine::difficulty::Difficulty;
+use crate::engine::mcts::{MctsLimits, Playout};
+use crate::engine::network::Network;
+use crate::engine::search::{SearchLimits, Searcher};
+use crate::game::{Checkerboard, MoveError, MoveResult, PlayerColor};
 use crate::game::bitboard::Bitboard;
-use crate::game::moves::{color_captures, color_moves};
-use crate::interface::ui::{CheckersView, help};
+use crate::game::moves::{color_captures, color_moves, legal_moves, Move, resolve_move};
+use crate::game::notation::{NotationError, PDN_FIRST_MOVER, to_fen};
+use crate::game::pdn::PdnGame;
+use crate::interface::clock::{Clock, TimeControl};
+use crate::interface::movelist::MoveListView;
+use crate::interface::theme::Theme;
+use crate::interface::ui::{autosave_path, CheckersView, help, load_dialog, new_game_dialog, next_puzzle, quit, refresh,
+                          resume_prompt, save_dialog, settings, show_analysis, show_ply, toggle_analysis};
 
+pub mod analysis;
+pub mod cli;
+pub mod clock;
+pub mod commands;
+pub mod movelist;
+pub mod protocol;
+pub mod puzzles;
+pub mod setup;
+pub mod theme;
 pub mod ui;
 
 pub struct CheckersGame {
   board: Checkerboard,
   on_move: PlayerColor,
   result: Option<GameResult>,
+  start: (Checkerboard, PlayerColor),
+  history: Vec<Move>,
+  current: Vec<Bitboard>,
+  clock: Option<Clock>,
 }
 
 impl CheckersGame {
   pub fn new() -> CheckersGame {
-    CheckersGame { board: Checkerboard::new(), on_move: PlayerColor::White, result: None  }
+    CheckersGame::from_position(Checkerboard::new(), PlayerColor::White)
+  }
+
+  pub fn from_position(board: Checkerboard, on_move: PlayerColor) -> CheckersGame {
+    let mut game = CheckersGame {
+      board,
+      on_move,
+      result: None,
+      start: (board, on_move),
+      history: Vec::new(),
+      current: Vec::new(),
+      clock: None,
+    };
+    game.result = game.game_over();
+    game
+  }
+
+  /**
+  Replays a PDN game, starting from its `FEN` tag if it has one. A `TimeControl` tag starts a clock,
+  set from the `WhiteClock` and `BlackClock` tags if present, and a decisive or drawn result ends
+  the game even if it could go on.
+   */
+  pub fn from_pdn(pdn: &PdnGame) -> Result<CheckersGame, NotationError> {
+    let (board, color) = pdn.start_position()?;
+    let mut game = CheckersGame::from_position(board, color);
+    for mv in &pdn.moves {
+      if let MoveResult::Invalid(error) = game.play_move(mv) {
+        return Err(NotationError::BadPdn(format!("illegal move {}: {}", mv, error)));
+      }
+    }
+    if game.result.is_none() {
+      match pdn.result.as_str() {
+        "1-0" | "2-0" => game.set_result(GameResult::Victory(PlayerColor::White)),
+        "0-1" | "0-2" => game.set_result(GameResult::Victory(PlayerColor::Black)),
+        "1/2-1/2" | "1-1" => game.set_result(GameResult::Draw),
+        _ => {}
+      }
+    }
+    if let Some(control) = pdn.tag("TimeControl") {
+      let control = control.parse::<TimeControl>()
+          .map_err(|e| NotationError::BadPdn(format!("bad TimeControl tag: {}", e)))?;
+      game.set_clock(control);
+      for (tag, color) in [("WhiteClock", PlayerColor::White), ("BlackClock", PlayerColor::Black)] {
+        if let Some(value) = pdn.tag(tag) {
+          let seconds = value.parse::<f64>().ok().filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+              .ok_or_else(|| NotationError::BadPdn(format!("bad {} tag {}", tag, value)))?;
+          if let Some(clock) = &mut game.clock {
+            clock.set_remaining(color, Duration::from_secs_f64(seconds));
+          }
+        }
+      }
+    }
+    Ok(game)
+  }
+
+  /// Starts the clock with the side to move running.
+  pub fn set_clock(&mut self, control: TimeControl) {
+    let mut clock = Clock::new(control);
+    if self.result.is_none() {
+      clock.start(self.on_move);
+    }
+    self.clock = Some(clock);
+  }
+
+  pub fn board(&self) -> &Checkerboard {
+    &self.board
+  }
+
+  pub fn on_move(&self) -> PlayerColor {
+    self.on_move
+  }
+
+  pub fn result(&self) -> Option<GameResult> {
+    self.result
+  }
+
+  pub fn start_position(&self) -> (Checkerboard, PlayerColor) {
+    self.start
+  }
+
+  /// The player and square of a piece that has started a capture sequence but not finished it.
+  pub fn pending_capture(&self) -> Option<(PlayerColor, Bitboard)> {
+    self.board.pending_capture()
+  }
+
+  /// Every completed move so far, in order.
+  pub fn history(&self) -> &[Move] {
+    &self.history
+  }
+
+  /// The position after the first `ply` moves of the game, and the side to move in it.
+  pub fn position_at(&self, ply: usize) -> (Checkerboard, PlayerColor) {
+    let (mut board, mut color) = self.start;
+    for mv in self.history.iter().take(ply) {
+      board.apply(color, mv);
+      color = !color;
+    }
+    (board, color)
+  }
+
+  pub fn clock(&self) -> Option<&Clock> {
+    self.clock.as_ref()
+  }
+
+  pub fn legal_moves(&self) -> Vec<Move> {
+    legal_moves(&self.board, self.on_move)
+  }
+
+  /**
+  Plays a complete move for the side on move. `mv` may abbreviate a multi-jump by leaving out
+  intermediate squares as long as only one legal capture sequence fits.
+   */
+  pub fn play_move(&mut self, mv: &Move) -> MoveResult {
+    if self.result.is_some() {
+      return MoveResult::Invalid(MoveError::GameOver);
+    }
+    if let Some((_, square)) = self.board.pending_capture() {
+      return MoveResult::Invalid(MoveError::MustContinueCapture(square));
+    }
+    let legal = match resolve_move(&self.board, self.on_move, mv) {
+      Ok(legal) => legal,
+      Err(error) => return MoveResult::Invalid(error),
+    };
+    self.board.apply(self.on_move, &legal);
+    self.finish_move(legal);
+    MoveResult::Valid(self.on_move)
+  }
+
+  /**
+  Plays a single step of a move, as the interactive board does. Returns the color of the player who
+  will make the next move, which stays the same while a capture sequence continues.
+   */
+  pub fn play_step(&mut self, start_square: Bitboard, end_square: Bitboard) -> MoveResult {
+    if self.result.is_some() {
+      return MoveResult::Invalid(MoveError::GameOver);
+    }
+    let result = self.board.make_move(self.on_move, start_square, end_square);
+    if let MoveResult::Valid(color) = result {
+      if self.current.is_empty() {
+        self.current.push(start_square);
+      }
+      self.current.push(end_square);
+      if color != self.on_move {
+        let path = std::mem::take(&mut self.current);
+        self.finish_move(Move::new(path));
+      }
+    }
+    result
+  }
+
+  fn finish_move(&mut self, mv: Move) {
+    self.history.push(mv);
+    self.on_move = !self.on_move;
+    if let Some(clock) = &mut self.clock {
+      clock.press();
+    }
+    self.result = self.game_over();
+    self.check_clock();
+  }
+
+  /// Ends the game with `result` regardless of the position, e.g. after a resignation or adjudication.
+  pub fn set_result(&mut self, result: GameResult) {
+    self.result = Some(result);
+    self.current.clear();
+    if let Some(clock) = &mut self.clock {
+      clock.stop();
+    }
+  }
+
+  /// Ends the game if the side to move has run out of time. Returns the result if the game is over.
+  pub fn check_clock(&mut self) -> Option<GameResult> {
+    if let Some(clock) = &mut self.clock {
+      if self.result.is_none() {
+        if let Some(color) = clock.flagged() {
+          self.result = Some(GameResult::Victory(!color));
+        }
+      }
+      if self.result.is_some() {
+        clock.stop();
+      }
+    }
+    self.result
+  }
+
+  /**
+  The game so far as PDN, with a `FEN` tag unless it started from the standard position with
+  `PDN_FIRST_MOVER` to move, as a new game with White to move does not, and the time control and
+  remaining times if it has a clock.
+   */
+  pub fn to_pdn(&self) -> PdnGame {
+    let mut pdn = PdnGame::new();
+    pdn.set_tag("Event", "Casual game");
+    pdn.set_tag("White", "White");
+    pdn.set_tag("Black", "Black");
+    pdn.set_tag("Result", GameResult::pdn_result(self.result));
+    if self.start != (Checkerboard::new(), PDN_FIRST_MOVER) {
+      pdn.set_tag("SetUp", "1");
+      pdn.set_tag("FEN", to_fen(&self.start.0, self.start.1));
+    }
+    if let Some(clock) = &self.clock {
+      pdn.set_tag("TimeControl", clock.control().to_string());
+      pdn.set_tag("WhiteClock", format!("{:.1}", clock.remaining(PlayerColor::White).as_secs_f64()));
+      pdn.set_tag("BlackClock", format!("{:.1}", clock.remaining(PlayerColor::Black).as_secs_f64()));
+    }
+    pdn.moves = self.history.clone();
+    pdn.result = GameResult::pdn_result(self.result).to_string();
+    pdn
   }
 
   pub fn game_over(&self) -> Option<GameResult> {
@@ -37,22 +271,167 @@ impl CheckersGame {
   }
 }
 
+impl Default for CheckersGame {
+  fn default() -> Self {
+    CheckersGame::new()
+  }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum GameResult {
   Victory(PlayerColor),
   Draw
 }
 
-pub fn run() {
+impl GameResult {
+  /// The PDN result token for a finished or unfinished (`None`) game.
+  pub fn pdn_result(result: Option<GameResult>) -> &'static str {
+    match result {
+      Some(GameResult::Victory(PlayerColor::White)) => "1-0",
+      Some(GameResult::Victory(PlayerColor::Black)) => "0-1",
+      Some(GameResult::Draw) => "1/2-1/2",
+      None => "*",
+    }
+  }
+}
+
+impl Display for GameResult {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      GameResult::Victory(color) => write!(f, "{} won!", color),
+      GameResult::Draw => f.write_str("It was a draw!"),
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PlayerKind {
+  Human,
+  /// The alpha-beta search.
+  Computer,
+  /// Monte Carlo tree search.
+  Mcts,
+}
+
+impl Display for PlayerKind {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      PlayerKind::Human => f.write_str("Human"),
+      PlayerKind::Computer => f.write_str("Computer"),
+      PlayerKind::Mcts => f.write_str("MCTS"),
+    }
+  }
+}
+
+/// Settings for a game in the terminal UI.
+#[derive(Clone, Debug)]
+pub struct PlayOptions {
+  pub white: PlayerKind,
+  pub black: PlayerKind,
+  pub position: Option<(Checkerboard, PlayerColor)>,
+  pub time_control: Option<TimeControl>,
+  /// Search depth of the computer player in games without a clock; with one it plans its own time.
+  pub depth: u32,
+  /// Makes the alpha-beta player weaker and more human; `None` plays at full strength to `depth`.
+  pub difficulty: Option<Difficulty>,
+  /// Number of threads for the computer's searches and the analysis panel.
+  pub threads: usize,
+  /// Whether the computer keeps searching on the player's time, assuming they make the reply it expects.
+  pub ponder: bool,
+  /// Budget of the MCTS player for each move.
+  pub mcts: MctsLimits,
+  pub playout: Playout,
+  /// Evaluates positions in the computer's searches instead of the handwritten evaluation.
+  pub network: Option<Arc<Network>>,
+  pub theme: Theme,
+  /// Number of candidate moves shown by the analysis panel.
+  pub analysis_lines: usize,
+  /// Whether to open the analysis panel at the start.
+  pub analysis: bool,
+  /// How many hints a player may ask for in each game; `None` for no limit.
+  pub hints: Option<u32>,
+  /// Where puzzle mode takes its puzzles from; `None` for the bundled ones.
+  pub puzzle_file: Option<PathBuf>,
+  /// Whether to start with a puzzle instead of a game.
+  pub puzzles: bool,
+}
+
+impl PlayOptions {
+  pub fn player(&self, color: PlayerColor) -> PlayerKind {
+    match color {
+      PlayerColor::White => self.white,
+      PlayerColor::Black => self.black,
+    }
+  }
+
+  /// A searcher with `limits` and the configured threads and network.
+  pub fn searcher(&self, limits: SearchLimits) -> Searcher {
+    Searcher::new(limits).with_threads(self.threads).with_network(self.network.clone())
+  }
+}
+
+impl Default for PlayOptions {
+  fn default() -> Self {
+    PlayOptions {
+      white: PlayerKind::Human,
+      black: PlayerKind::Human,
+      position: None,
+      time_control: None,
+      depth: 8,
+      difficulty: None,
+      threads: 1,
+      ponder: false,
+      mcts: MctsLimits { iterations: Some(20_000), time: None },
+      playout: Playout::Heuristic,
+      network: None,
+      theme: Theme::default(),
+      analysis_lines: 3,
+      analysis: false,
+      hints: None,
+      puzzle_file: None,
+      puzzles: false,
+    }
+  }
+}
+
+/// The directory for this program's configuration and saved state, e.g. `~/.config/checkers`.
+pub fn config_dir() -> Option<PathBuf> {
+  dirs::config_dir().map(|dir| dir.join("checkers"))
+}
+
+pub fn run(options: PlayOptions) {
   let mut siv = cursive::default();
 
   siv.add_global_callback('?', help);
-  siv.add_global_callback('q', Cursive::quit);
+  siv.add_global_callback('o', settings);
+  siv.add_global_callback('n', new_game_dialog);
+  siv.add_global_callback('s', save_dialog);
+  siv.add_global_callback('l', load_dialog);
+  siv.add_global_callback('a', toggle_analysis);
+  siv.add_global_callback('p', next_puzzle);
+  siv.add_global_callback('q', quit);
+  siv.add_global_callback(Event::CtrlChar('c'), quit);
+  // refresh events drive the clocks and pick up computer moves
+  siv.add_global_callback(Event::Refresh, refresh);
+  siv.set_fps(10);
 
-  let game_view = CheckersView::new().with_name("board");
+  let resume = options.position.is_none() && !options.puzzles && autosave_path().is_some_and(|path| path.is_file());
+  let puzzles = options.puzzles;
+  let analysis = options.analysis;
+  let game_view = CheckersView::new(options).with_wake(siv.cb_sink().clone()).with_name("board");
   let view = Dialog::around(game_view).title("Checkers");
+  let moves = Panel::new(MoveListView::new().on_select(show_ply).with_name("moves")).title("Moves");
 
-  siv.add_layer(view);
+  siv.add_layer(LinearLayout::horizontal().child(view).child(moves).with_name("main"));
+  if analysis {
+    show_analysis(&mut siv, true);
+  }
+  if puzzles {
+    next_puzzle(&mut siv);
+  }
+  if resume {
+    resume_prompt(&mut siv);
+  }
 
   siv.run();
 }
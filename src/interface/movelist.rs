@@ -0,0 +1,162 @@
+use std::rc::Rc;
+
+use cursive::{Cursive, Printer, Vec2, View};
+use cursive::direction::Direction;
+use cursive::event::{Event, EventResult, Key, MouseButton, MouseEvent};
+use cursive::theme::ColorStyle;
+use cursive::view::CannotFocus;
+
+/// Room for the move numbers left of the moves.
+const NUMBER_WIDTH: usize = 5;
+/// The narrowest column, which fits all but long multi-jumps.
+const MIN_COLUMN: usize = 10;
+const HEIGHT: usize = 35;
+
+type SelectCallback = Rc<dyn Fn(&mut Cursive, usize)>;
+
+/**
+Side panel listing the moves of the game in numbered pairs, with the ply currently shown on the
+board highlighted. The board view pushes updates to it and it scrolls to keep the highlighted move
+visible. The columns widen to fit the longest move. When focused, Up, Down and the paging keys step
+through the moves and End or Esc goes back to the game; clicking a move, focused or not, picks it.
+ */
+pub struct MoveListView {
+  moves: Vec<String>,
+  black_first: bool,
+  current: usize,
+  scroll: usize,
+  /// Called with the number of plies played in the picked position.
+  on_select: Option<SelectCallback>,
+}
+
+impl MoveListView {
+  pub fn new() -> MoveListView {
+    MoveListView { moves: Vec::new(), black_first: false, current: 0, scroll: 0, on_select: None }
+  }
+
+  /// Calls `callback` with the number of plies played in the position after a picked move.
+  pub fn on_select(mut self, callback: impl Fn(&mut Cursive, usize) + 'static) -> MoveListView {
+    self.on_select = Some(Rc::new(callback));
+    self
+  }
+
+  /**
+  Replaces the list. `black_first` is set when the game started with Black to move, and `current`
+  is the number of plies played in the position on the board (0 highlights nothing).
+   */
+  pub fn set_moves(&mut self, moves: Vec<String>, black_first: bool, current: usize) {
+    self.moves = moves;
+    self.black_first = black_first;
+    self.current = current.min(self.moves.len());
+  }
+
+  /// The row and column (0 for White, 1 for Black) of the move at index `ply`.
+  fn cell(&self, ply: usize) -> (usize, usize) {
+    let slot = ply + self.black_first as usize;
+    (slot / 2, slot % 2)
+  }
+
+  fn rows(&self) -> usize {
+    if self.moves.is_empty() {
+      0
+    } else {
+      self.cell(self.moves.len() - 1).0 + 1
+    }
+  }
+
+  /// The width of a column, with two spaces after the longest move.
+  fn column_width(&self) -> usize {
+    self.moves.iter().map(|mv| mv.chars().count() + 2).max().unwrap_or(0).max(MIN_COLUMN)
+  }
+
+  /// The number of plies played after the move drawn at `position`, if there is one.
+  fn ply_at(&self, position: Vec2) -> Option<usize> {
+    let column = position.x.checked_sub(NUMBER_WIDTH)? / self.column_width();
+    if column > 1 {
+      return None;
+    }
+    let slot = (self.scroll + position.y) * 2 + column;
+    let ply = slot.checked_sub(self.black_first as usize)?;
+    (ply < self.moves.len()).then_some(ply + 1)
+  }
+
+  fn select(&mut self, ply: usize) -> EventResult {
+    let ply = ply.min(self.moves.len());
+    self.current = ply;
+    match self.on_select.clone() {
+      Some(callback) => EventResult::with_cb(move |s| callback(s, ply)),
+      None => EventResult::Consumed(None),
+    }
+  }
+}
+
+impl Default for MoveListView {
+  fn default() -> Self {
+    MoveListView::new()
+  }
+}
+
+impl View for MoveListView {
+  fn draw(&self, printer: &Printer) {
+    let width = self.column_width();
+    let highlight = if printer.focused { ColorStyle::highlight() } else { ColorStyle::highlight_inactive() };
+    for row in self.scroll..self.rows().min(self.scroll + printer.size.y) {
+      let y = row - self.scroll;
+      printer.print((0, y), &format!("{:>3}.", row + 1));
+      for column in 0..2 {
+        let slot = row * 2 + column;
+        let text = match slot.checked_sub(self.black_first as usize).and_then(|ply| self.moves.get(ply)) {
+          Some(mv) => mv.as_str(),
+          None if slot == 0 => "...",
+          None => continue,
+        };
+        let style = if slot + 1 == self.current + self.black_first as usize { highlight } else { ColorStyle::primary() };
+        printer.with_color(style, |printer| printer.print((NUMBER_WIDTH + width * column, y), text));
+      }
+    }
+  }
+
+  fn layout(&mut self, size: Vec2) {
+    let height = size.y.max(1);
+    let rows = self.rows();
+    // keep the highlighted move, or the end of the game, in view
+    let row = if self.current == 0 { 0 } else { self.cell(self.current - 1).0 };
+    if row < self.scroll {
+      self.scroll = row;
+    } else if row >= self.scroll + height {
+      self.scroll = row + 1 - height;
+    }
+    self.scroll = self.scroll.min(rows.saturating_sub(height));
+  }
+
+  fn required_size(&mut self, _: Vec2) -> Vec2 {
+    Vec2::new(NUMBER_WIDTH + 2 * self.column_width(), HEIGHT)
+  }
+
+  fn take_focus(&mut self, _: Direction) -> Result<EventResult, CannotFocus> {
+    if self.moves.is_empty() {
+      Err(CannotFocus)
+    } else {
+      Ok(EventResult::Consumed(None))
+    }
+  }
+
+  // Left and Right are left to the layout, so they move the focus back to the board
+  fn on_event(&mut self, event: Event) -> EventResult {
+    match event {
+      Event::Key(Key::Up | Key::PageUp) => self.select(self.current.saturating_sub(1)),
+      Event::Key(Key::Down | Key::PageDown) => self.select(self.current + 1),
+      Event::Key(Key::Home) => self.select(0),
+      Event::Key(Key::End | Key::Esc) => self.select(self.moves.len()),
+      Event::Mouse { event: MouseEvent::WheelUp, .. } => self.select(self.current.saturating_sub(1)),
+      Event::Mouse { event: MouseEvent::WheelDown, .. } => self.select(self.current + 1),
+      Event::Mouse { offset, position, event: MouseEvent::Press(MouseButton::Left) } => {
+        match position.checked_sub(offset).and_then(|position| self.ply_at(position)) {
+          Some(ply) => self.select(ply),
+          None => EventResult::Ignored,
+        }
+      }
+      _ => EventResult::Ignored,
+    }
+  }
+}
@@ -0,0 +1,146 @@
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+use eyre::{eyre, Result};
+
+use crate::engine::search::{format_score, SearchInfo, SearchLimits, Searcher};
+use crate::engine::time::ClockTime;
+use crate::game::{Checkerboard, PlayerColor};
+use crate::game::notation::{parse_fen, to_fen};
+use crate::game::moves::resolve_move;
+
+/*  Engine protocol, one command per line:
+      position startpos [moves <move>...]
+      position fen <fen> [moves <move>...]
+      go [depth <plies>] [movetime <ms>] [nodes <count>]
+         [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>] [movestogo <moves>]
+                         with the clocks the engine plans its own time, moving at once
+                         when only one move is legal
+      isready            -> readyok
+      newgame            also clears the transposition table
+      board              print the current position
+      quit
+    A search prints an `info` line per completed iteration followed by `bestmove <move>`
+    (or `bestmove none` when there is no legal move).
+ */
+
+/// Runs the engine protocol on stdin and stdout, searching with `searcher`.
+pub fn run_engine(searcher: Searcher) -> Result<()> {
+  let stdin = std::io::stdin();
+  let stdout = std::io::stdout();
+  serve(stdin.lock(), stdout.lock(), searcher)
+}
+
+/**
+Answers engine protocol commands read from `input` until `quit` or the end of the input. Searches
+share a transposition table until `newgame`.
+ */
+pub fn serve<R: BufRead, W: Write>(input: R, mut output: W, mut searcher: Searcher) -> Result<()> {
+  let mut board = Checkerboard::new();
+  let mut color = PlayerColor::White;
+
+  for line in input.lines() {
+    let line = line?;
+    let mut words = line.split_whitespace();
+    let outcome = match words.next() {
+      None => Ok(()),
+      Some("quit") => break,
+      Some("isready") => writeln!(output, "readyok").map_err(Into::into),
+      Some("newgame") => {
+        board = Checkerboard::new();
+        color = PlayerColor::White;
+        searcher.clear_table();
+        Ok(())
+      }
+      Some("board") => writeln!(output, "{}{}", board, to_fen(&board, color)).map_err(Into::into),
+      Some("position") => set_position(&words.collect::<Vec<_>>())
+          .map(|(new_board, new_color)| {
+            board = new_board;
+            color = new_color;
+          }),
+      Some("go") => parse_limits(&words.collect::<Vec<_>>(), color)
+          .and_then(|limits| {
+            searcher.set_limits(limits);
+            go(&board, color, &mut searcher, &mut output)
+          }),
+      Some(command) => Err(eyre!("unknown command '{}'", command)),
+    };
+    if let Err(e) = outcome {
+      writeln!(output, "error {}", e)?;
+    }
+    output.flush()?;
+  }
+  Ok(())
+}
+
+fn set_position(words: &[&str]) -> Result<(Checkerboard, PlayerColor)> {
+  let moves_at = words.iter().position(|&w| w == "moves").unwrap_or(words.len());
+  let (mut board, mut color) = match words.first() {
+    Some(&"startpos") => (Checkerboard::new(), PlayerColor::White),
+    Some(&"fen") => parse_fen(&words[1..moves_at].join(""))?,
+    _ => return Err(eyre!("expected 'startpos' or 'fen'")),
+  };
+  for word in words.iter().skip(moves_at + 1) {
+    let mv = resolve_move(&board, color, &word.parse()?)
+        .map_err(|e| eyre!("illegal move {}: {}", word, e))?;
+    board.apply(color, &mv);
+    color = !color;
+  }
+  Ok((board, color))
+}
+
+/// Reads the limits of a `go` command for `color`, who is to move.
+fn parse_limits(words: &[&str], color: PlayerColor) -> Result<SearchLimits> {
+  let mut limits = SearchLimits::default();
+  let mut clock = ClockTime::default();
+  let mut has_clock = false;
+  let (time, increment) = match color {
+    PlayerColor::White => ("wtime", "winc"),
+    PlayerColor::Black => ("btime", "binc"),
+  };
+  for pair in words.chunks(2) {
+    let value = pair.get(1).ok_or_else(|| eyre!("missing value for '{}'", pair[0]))?;
+    match pair[0] {
+      "depth" => limits.depth = Some(value.parse()?),
+      "movetime" => limits.time = Some(Duration::from_millis(value.parse()?)),
+      "nodes" => limits.nodes = Some(value.parse()?),
+      word if word == time => {
+        clock.remaining = Duration::from_millis(value.parse()?);
+        has_clock = true;
+      }
+      word if word == increment => clock.increment = Duration::from_millis(value.parse()?),
+      "movestogo" => clock.moves_to_go = Some(value.parse()?),
+      // the opponent's clock
+      "wtime" | "btime" | "winc" | "binc" => {
+        value.parse::<u64>()?;
+      }
+      other => return Err(eyre!("unknown search limit '{}'", other)),
+    }
+  }
+  limits.clock = has_clock.then_some(clock);
+  Ok(limits)
+}
+
+fn go<W: Write>(board: &Checkerboard, color: PlayerColor, searcher: &mut Searcher, output: &mut W) -> Result<()> {
+  let mut write_error = None;
+  let info = searcher.search_with(board, color, |info| {
+    if let Err(e) = writeln!(output, "{}", info_line(info)) {
+      write_error.get_or_insert(e);
+    }
+  });
+  if let Some(e) = write_error {
+    return Err(e.into());
+  }
+  match info.best_move() {
+    Some(mv) => writeln!(output, "bestmove {}", mv)?,
+    None => writeln!(output, "bestmove none")?,
+  }
+  Ok(())
+}
+
+/// Formats a completed search iteration as a protocol `info` line.
+pub fn info_line(info: &SearchInfo) -> String {
+  let pv: Vec<String> = info.pv.iter().map(ToString::to_string).collect();
+  format!("info depth {} score {} nodes {} time {} pv {}",
+          info.depth, format_score(info.score), info.nodes, info.elapsed.as_millis(), pv.join(" "))
+}
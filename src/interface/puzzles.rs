@@ -0,0 +1,332 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use eyre::{eyre, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::eval::{evaluate, KING_VALUE, MAN_VALUE};
+use crate::engine::search::{MATE_BOUND, Searcher};
+use crate::game::{Checkerboard, MoveResult, PlayerColor};
+use crate::game::moves::{color_captures, resolve_move, Move};
+use crate::game::notation::{parse_fen, to_fen};
+use crate::interface::config_dir;
+
+/// The puzzles that come with the program.
+const BUNDLED: &str = include_str!("puzzles.toml");
+/// Rating of puzzles that have none, and of a player who has not solved any yet.
+pub const DEFAULT_RATING: i32 = 1200;
+/// How far a player's rating moves after a puzzle against an equally rated one, times two.
+const RATING_FACTOR: f64 = 32.0;
+/// A winning move other than the solution's is accepted if it scores at most this much worse.
+const ALTERNATIVE_MARGIN: i32 = 50;
+/// Games are searched for puzzles only in positions whose static evaluation is within this range.
+const EXTRACT_BALANCE: i32 = 150;
+/**
+A position holds a puzzle if its best move scores at least `SHOT_GAIN` above the static evaluation
+while the second best scores at most `OTHER_GAIN` above it.
+ */
+pub const SHOT_GAIN: i32 = 120;
+pub const OTHER_GAIN: i32 = 40;
+
+/// A position where the side to move has a winning continuation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Puzzle {
+  pub board: Checkerboard,
+  pub color: PlayerColor,
+  /// Moves of both sides, starting and ending with the solver's.
+  pub solution: Vec<Move>,
+  /// What the winning idea is, e.g. `shot` or `breakthrough`.
+  pub theme: String,
+  pub rating: i32,
+}
+
+impl Puzzle {
+  /// Identifies the puzzle in the player's history.
+  pub fn id(&self) -> String {
+    to_fen(&self.board, self.color)
+  }
+
+  fn from_record(record: &PuzzleRecord) -> Result<Puzzle> {
+    let (board, color) = parse_fen(&record.fen)?;
+    let mut position = (board, color);
+    let mut solution = Vec::new();
+    for written in record.solution.split_whitespace() {
+      let written: Move = written.parse()?;
+      let mv = resolve_move(&position.0, position.1, &written)
+          .map_err(|error| eyre!("illegal move {} in the solution: {}", written, error))?;
+      position.0.apply(position.1, &mv);
+      position.1 = !position.1;
+      solution.push(mv);
+    }
+    if solution.len().is_multiple_of(2) {
+      return Err(eyre!("the solution must end with a move of the side to play"));
+    }
+    Ok(Puzzle { board, color, solution, theme: record.theme.clone(), rating: record.rating.unwrap_or(DEFAULT_RATING) })
+  }
+
+  pub fn to_record(&self) -> PuzzleRecord {
+    let solution: Vec<String> = self.solution.iter().map(Move::to_string).collect();
+    PuzzleRecord {
+      fen: self.id(),
+      solution: solution.join(" "),
+      theme: self.theme.clone(),
+      rating: Some(self.rating),
+    }
+  }
+}
+
+/// A puzzle as written in a puzzle file.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PuzzleRecord {
+  pub fen: String,
+  /// Moves separated by spaces.
+  pub solution: String,
+  pub theme: String,
+  pub rating: Option<i32>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct PuzzleFile {
+  #[serde(default)]
+  puzzle: Vec<PuzzleRecord>,
+}
+
+/// Parses a puzzle file: a `[[puzzle]]` table per puzzle with `fen`, `solution`, `theme` and `rating`.
+pub fn parse_puzzles(text: &str) -> Result<Vec<Puzzle>> {
+  let file: PuzzleFile = toml::from_str(text)?;
+  file.puzzle.iter().enumerate()
+      .map(|(i, record)| Puzzle::from_record(record).wrap_err_with(|| format!("puzzle {} ({})", i + 1, record.fen)))
+      .collect()
+}
+
+/// Writes puzzles in the format read by `parse_puzzles`.
+pub fn write_puzzles(puzzles: &[Puzzle]) -> Result<String> {
+  let file = PuzzleFile { puzzle: puzzles.iter().map(Puzzle::to_record).collect() };
+  Ok(toml::to_string(&file)?)
+}
+
+/// Loads the puzzles in `path`, or the bundled ones.
+pub fn load_puzzles(path: Option<&Path>) -> Result<Vec<Puzzle>> {
+  match path {
+    Some(path) => {
+      let text = fs::read_to_string(path).wrap_err_with(|| format!("could not read {}", path.display()))?;
+      parse_puzzles(&text).wrap_err_with(|| format!("invalid puzzle file {}", path.display()))
+    }
+    None => parse_puzzles(BUNDLED),
+  }
+}
+
+/// A puzzle found in a game.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FoundPuzzle {
+  pub puzzle: Puzzle,
+  /// Index in the game's moves of the move played in the puzzle's position.
+  pub ply: usize,
+  /// Whether the player found the solution's first move.
+  pub found: bool,
+}
+
+/**
+Replays a game from `start` and searches the position before each of `moves` for a unique winning
+shot: a multi-jump combination or a breakthrough to a king that wins material, where every other
+move falls well short. The principal variation is cut after the solver's move that completes the
+win, once the opponent has nothing to capture back.
+ */
+pub fn extract_puzzles(start: (Checkerboard, PlayerColor), moves: &[Move], searcher: &mut Searcher) -> Vec<FoundPuzzle> {
+  let (mut board, mut color) = start;
+  let mut found = Vec::new();
+  for (ply, played) in moves.iter().enumerate() {
+    let base = evaluate(&board, color);
+    if base.abs() <= EXTRACT_BALANCE {
+      let lines = searcher.search_lines(&board, color, 2, |_| {});
+      if let [best, second] = lines.as_slice() {
+        if best.score >= base + SHOT_GAIN && second.score <= base + OTHER_GAIN {
+          if let Some(puzzle) = shot_puzzle(&board, color, &best.pv) {
+            let found_it = puzzle.solution[0] == *played;
+            found.push(FoundPuzzle { puzzle, ply, found: found_it });
+          }
+        }
+      }
+    }
+    if let MoveResult::Invalid(_) = board.apply(color, played) {
+      break;
+    }
+    color = !color;
+  }
+  found
+}
+
+/// Turns a winning line into a puzzle if it ends in won material and is a shot or a breakthrough.
+fn shot_puzzle(board: &Checkerboard, color: PlayerColor, pv: &[Move]) -> Option<Puzzle> {
+  let start = material(board, color);
+  let mut position = *board;
+  let mut mover = color;
+  let mut theme = None;
+  for (i, mv) in pv.iter().enumerate() {
+    let kings = position.kings(color).count();
+    position.apply(mover, mv);
+    mover = !mover;
+    if mover == color {
+      continue;
+    }
+    if mv.is_capture() && mv.path().len() > 2 {
+      theme = Some("shot");
+    } else if position.kings(color).count() > kings {
+      theme = theme.or(Some("breakthrough"));
+    }
+    if material(&position, color) >= start + KING_VALUE - MAN_VALUE && color_captures(&position, mover).is_empty() {
+      let solution = pv[..=i].to_vec();
+      let rating = estimate_rating(&solution);
+      return theme.map(|theme| Puzzle { board: *board, color, solution, theme: theme.to_string(), rating });
+    }
+  }
+  None
+}
+
+/// The material of `color` less that of its opponent.
+fn material(board: &Checkerboard, color: PlayerColor) -> i32 {
+  let side = |color| board.men(color).count() as i32 * MAN_VALUE + board.kings(color).count() as i32 * KING_VALUE;
+  side(color) - side(!color)
+}
+
+/// A rough rating: longer solutions are harder, and so are those starting with a quiet move.
+fn estimate_rating(solution: &[Move]) -> i32 {
+  let solver_moves = solution.len().div_ceil(2) as i32;
+  let quiet = if solution[0].is_capture() { 0 } else { 150 };
+  900 + 100 * solver_moves + quiet
+}
+
+/// What became of a move made while solving a puzzle.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PuzzleStep {
+  /// The move is right; the opponent answers with `reply`.
+  Correct { reply: Move },
+  /// The move completes the solution or wins in another way.
+  Solved,
+  /// The move throws the win away; `solution` was the move to play.
+  Wrong { solution: Move },
+}
+
+/// Progress through one puzzle.
+#[derive(Clone, Debug)]
+pub struct PuzzleAttempt {
+  pub puzzle: Puzzle,
+  /// Number of solution moves played so far.
+  progress: usize,
+  /// Whether the puzzle is solved (`Some(true)`) or failed (`Some(false)`).
+  pub outcome: Option<bool>,
+}
+
+impl PuzzleAttempt {
+  pub fn new(puzzle: Puzzle) -> PuzzleAttempt {
+    PuzzleAttempt { puzzle, progress: 0, outcome: None }
+  }
+
+  /**
+  Checks the solver's move `played` from position `board`. A move other than the solution's is
+  accepted when `searcher` scores it as a win, at least `SHOT_GAIN`, and about as well as the
+  solution's move, so alternative wins count as solved; the solution's remaining moves no longer
+  apply after one.
+   */
+  pub fn check(&mut self, board: &Checkerboard, played: &Move, searcher: &mut Searcher) -> PuzzleStep {
+    let expected = self.puzzle.solution[self.progress].clone();
+    if expected.matches(played) {
+      self.progress += 1;
+      if let Some(reply) = self.puzzle.solution.get(self.progress).cloned() {
+        self.progress += 1;
+        return PuzzleStep::Correct { reply };
+      }
+    } else {
+      let score = score_after(board, self.puzzle.color, played, searcher);
+      if score < SHOT_GAIN ||
+          score < score_after(board, self.puzzle.color, &expected, searcher).min(MATE_BOUND) - ALTERNATIVE_MARGIN {
+        self.outcome = Some(false);
+        return PuzzleStep::Wrong { solution: expected };
+      }
+    }
+    self.outcome = Some(true);
+    PuzzleStep::Solved
+  }
+}
+
+/// The score of the position after `color` plays `mv` on `board`, from `color`'s point of view.
+fn score_after(board: &Checkerboard, color: PlayerColor, mv: &Move, searcher: &mut Searcher) -> i32 {
+  let mut next = *board;
+  next.apply(color, mv);
+  -searcher.search(&next, !color).score
+}
+
+/// A player's puzzle rating and record, kept in the configuration directory.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PuzzleStats {
+  pub rating: i32,
+  pub solved: u32,
+  pub failed: u32,
+  /// Puzzles solved in a row, or failed in a row if negative.
+  pub streak: i32,
+  pub best_streak: u32,
+  /// Ids of the puzzles tried, oldest first.
+  pub attempted: Vec<String>,
+}
+
+impl Default for PuzzleStats {
+  fn default() -> Self {
+    PuzzleStats { rating: DEFAULT_RATING, solved: 0, failed: 0, streak: 0, best_streak: 0, attempted: Vec::new() }
+  }
+}
+
+impl PuzzleStats {
+  pub fn path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("puzzle_stats.toml"))
+  }
+
+  /// Reads the stats from `path`, starting afresh if the file does not exist.
+  pub fn load(path: &Path) -> Result<PuzzleStats> {
+    if !path.is_file() {
+      return Ok(PuzzleStats::default());
+    }
+    let text = fs::read_to_string(path).wrap_err_with(|| format!("could not read {}", path.display()))?;
+    toml::from_str(&text).wrap_err_with(|| format!("invalid puzzle stats {}", path.display()))
+  }
+
+  pub fn save(&self, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+      fs::create_dir_all(dir).wrap_err_with(|| format!("could not create {}", dir.display()))?;
+    }
+    fs::write(path, toml::to_string(self)?).wrap_err_with(|| format!("could not write {}", path.display()))
+  }
+
+  /// Records an attempt at `puzzle`, moving the rating as in the Elo system.
+  pub fn record(&mut self, puzzle: &Puzzle, solved: bool) {
+    let expected = 1.0 / (1.0 + 10f64.powf((puzzle.rating - self.rating) as f64 / 400.0));
+    let actual = if solved { 1.0 } else { 0.0 };
+    self.rating += (RATING_FACTOR * (actual - expected)).round() as i32;
+    if solved {
+      self.solved += 1;
+      self.streak = self.streak.max(0) + 1;
+      self.best_streak = self.best_streak.max(self.streak as u32);
+    } else {
+      self.failed += 1;
+      self.streak = self.streak.min(0) - 1;
+    }
+    let id = puzzle.id();
+    self.attempted.retain(|other| *other != id);
+    self.attempted.push(id);
+  }
+
+  /**
+  Picks the puzzle to try next: the one rated closest to the player among those not tried yet,
+  or, once all have been tried, the one tried longest ago.
+   */
+  pub fn next_puzzle<'a>(&self, puzzles: &'a [Puzzle]) -> Option<&'a Puzzle> {
+    let untried = puzzles.iter()
+        .filter(|puzzle| !self.attempted.contains(&puzzle.id()))
+        .min_by_key(|puzzle| (puzzle.rating - self.rating).abs());
+    untried.or_else(|| {
+      puzzles.iter().min_by_key(|puzzle| self.attempted.iter().position(|id| *id == puzzle.id()))
+    })
+  }
+}
@@ -0,0 +1,45 @@
+use crate::game::{Checkerboard, PlayerColor, PositionError};
+use crate::game::bitboard::Bitboard;
+
+/// A position being composed in the board editor.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Setup {
+  pub board: Checkerboard,
+  pub on_move: PlayerColor,
+}
+
+impl Setup {
+  /// Starts editing from `board`, leaving out any unfinished capture sequence.
+  pub fn new(board: &Checkerboard, on_move: PlayerColor) -> Setup {
+    use PlayerColor::{Black, White};
+    let board = Checkerboard::from_pieces(board.men(White), board.men(Black), board.kings(White), board.kings(Black));
+    Setup { board, on_move }
+  }
+
+  /**
+  Puts `piece` (color, and whether it is a king) on `square`, or clears the square for `None`.
+  Placements that make the position invalid, such as a man on its promotion row or a thirteenth
+  piece, are refused. A side without pieces is allowed while editing but not by `finish`.
+   */
+  pub fn place(&mut self, square: Bitboard, piece: Option<(PlayerColor, bool)>) -> Result<(), PositionError> {
+    let mut board = self.board;
+    board.set_square(square, piece);
+    match board.validate() {
+      Ok(()) | Err(PositionError::NoPieces(_)) => {
+        self.board = board;
+        Ok(())
+      }
+      Err(error) => Err(error),
+    }
+  }
+
+  pub fn clear(&mut self) {
+    self.board = Checkerboard::from_pieces(Bitboard::new(), Bitboard::new(), Bitboard::new(), Bitboard::new());
+  }
+
+  /// The finished position, if it is valid.
+  pub fn finish(&self) -> Result<(Checkerboard, PlayerColor), PositionError> {
+    self.board.validate()?;
+    Ok((self.board, self.on_move))
+  }
+}
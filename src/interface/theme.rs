@@ -0,0 +1,242 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use cursive::theme::ColorType;
+use eyre::{eyre, Result, WrapErr};
+use serde::Deserialize;
+
+use crate::game::{Checkerboard, PlayerColor};
+use crate::game::bitboard::Bitboard;
+use crate::interface::config_dir;
+
+/// Names of the built-in themes, any of which a theme file can start from with `preset = "..."`.
+pub const PRESETS: [&str; 3] = ["default", "ascii", "colorblind"];
+
+/// Characters used to draw the pieces and move hints.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PieceSet {
+  pub white_man: char,
+  pub black_man: char,
+  pub white_king: char,
+  pub black_king: char,
+  /// Marks the squares the selected piece can move to.
+  pub destination: char,
+}
+
+impl PieceSet {
+  /// The glyph and owner of the piece on `square`, if any.
+  pub fn glyph(&self, board: &Checkerboard, square: Bitboard) -> Option<(char, PlayerColor)> {
+    use PlayerColor::{Black, White};
+    if (board.men(White) & square).is_not_empty() {
+      Some((self.white_man, White))
+    } else if (board.men(Black) & square).is_not_empty() {
+      Some((self.black_man, Black))
+    } else if (board.kings(White) & square).is_not_empty() {
+      Some((self.white_king, White))
+    } else if (board.kings(Black) & square).is_not_empty() {
+      Some((self.black_king, Black))
+    } else {
+      None
+    }
+  }
+}
+
+/**
+Colors of the board. Backgrounds fill a whole cell; the others color the glyph in its center. Each
+may be a cursive palette name such as `primary` or `highlight`, a color name such as `light blue`,
+or `#rrggbb`.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoardColors {
+  pub dark_square: ColorType,
+  pub light_square: ColorType,
+  pub white_piece: ColorType,
+  pub black_piece: ColorType,
+  /// Background of the square under the cursor.
+  pub cursor: ColorType,
+  /// Background of the selected piece's square.
+  pub selected: ColorType,
+  pub destination: ColorType,
+  /// Pieces that are obliged to capture.
+  pub forced: ColorType,
+  /// Background of the squares the previous move passed through.
+  pub last_move: ColorType,
+  /// Background of the squares of the suggested move.
+  pub hint: ColorType,
+  pub label: ColorType,
+}
+
+/// Everything about the board's appearance that can be configured.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+  pub pieces: PieceSet,
+  pub colors: BoardColors,
+  /// Draws the grid with `+`, `-` and `|` for terminals without box-drawing characters.
+  pub ascii_lines: bool,
+}
+
+impl Theme {
+  /// Looks up a built-in theme by name.
+  pub fn preset(name: &str) -> Option<Theme> {
+    let color = |name: &str| ColorType::from_str(name).unwrap();
+    let default = Theme {
+      pieces: PieceSet { white_man: '○', black_man: '●', white_king: '☆', black_king: '★', destination: '•' },
+      colors: BoardColors {
+        dark_square: color("view"),
+        light_square: color("view"),
+        white_piece: color("primary"),
+        black_piece: color("primary"),
+        cursor: color("highlight"),
+        selected: color("highlight_inactive"),
+        destination: color("secondary"),
+        forced: color("title_primary"),
+        last_move: color("title_secondary"),
+        hint: color("tertiary"),
+        label: color("secondary"),
+      },
+      ascii_lines: false,
+    };
+    match name {
+      "default" => Some(default),
+      "ascii" => Some(Theme {
+        pieces: PieceSet { white_man: 'w', black_man: 'b', white_king: 'W', black_king: 'B', destination: '*' },
+        ascii_lines: true,
+        ..default
+      }),
+      // Okabe-Ito colors, which stay distinguishable with the common kinds of color blindness,
+      // and kings that differ from men by shape as well as by fill
+      "colorblind" => Some(Theme {
+        pieces: PieceSet { white_man: '○', black_man: '●', white_king: '◎', black_king: '◉', destination: '◆' },
+        colors: BoardColors {
+          dark_square: color("#d9d9d9"),
+          light_square: color("#ffffff"),
+          white_piece: color("#0072b2"),
+          black_piece: color("#000000"),
+          cursor: color("#e69f00"),
+          selected: color("#56b4e9"),
+          destination: color("#0072b2"),
+          forced: color("#d55e00"),
+          last_move: color("#f0e442"),
+          hint: color("#009e73"),
+          label: color("#000000"),
+        },
+        ..default
+      }),
+      _ => None,
+    }
+  }
+
+  /**
+  Loads the theme named by `choice`, which is either a preset name or the path of a theme file. With
+  no choice, `theme.toml` in the configuration directory is used if it exists, else the default.
+   */
+  pub fn load(choice: Option<&str>) -> Result<Theme> {
+    match choice {
+      Some(name) => match Theme::preset(name) {
+        Some(theme) => Ok(theme),
+        None => Theme::from_file(Path::new(name)),
+      },
+      None => match config_dir().map(|dir| dir.join("theme.toml")) {
+        Some(path) if path.is_file() => Theme::from_file(&path),
+        _ => Ok(Theme::default()),
+      },
+    }
+  }
+
+  pub fn from_file(path: &Path) -> Result<Theme> {
+    let text = fs::read_to_string(path).wrap_err_with(|| format!("could not read theme {}", path.display()))?;
+    Theme::from_toml(&text).wrap_err_with(|| format!("invalid theme {}", path.display()))
+  }
+
+  /**
+  Parses a theme file: an optional `preset` to start from, then `[pieces]` and `[colors]` tables
+  overriding any of its glyphs and colors, and an optional `ascii_lines` flag.
+   */
+  pub fn from_toml(text: &str) -> Result<Theme> {
+    let file: ThemeFile = toml::from_str(text)?;
+    let preset = file.preset.as_deref().unwrap_or("default");
+    let mut theme = Theme::preset(preset)
+        .ok_or_else(|| eyre!("unknown preset {}, expected one of {}", preset, PRESETS.join(", ")))?;
+
+    let pieces = &mut theme.pieces;
+    for (glyph, value) in [
+      (&mut pieces.white_man, file.pieces.white_man),
+      (&mut pieces.black_man, file.pieces.black_man),
+      (&mut pieces.white_king, file.pieces.white_king),
+      (&mut pieces.black_king, file.pieces.black_king),
+      (&mut pieces.destination, file.pieces.destination),
+    ] {
+      if let Some(value) = value {
+        *glyph = value;
+      }
+    }
+
+    let colors = &mut theme.colors;
+    for (color, value) in [
+      (&mut colors.dark_square, file.colors.dark_square),
+      (&mut colors.light_square, file.colors.light_square),
+      (&mut colors.white_piece, file.colors.white_piece),
+      (&mut colors.black_piece, file.colors.black_piece),
+      (&mut colors.cursor, file.colors.cursor),
+      (&mut colors.selected, file.colors.selected),
+      (&mut colors.destination, file.colors.destination),
+      (&mut colors.forced, file.colors.forced),
+      (&mut colors.last_move, file.colors.last_move),
+      (&mut colors.hint, file.colors.hint),
+      (&mut colors.label, file.colors.label),
+    ] {
+      if let Some(value) = value {
+        *color = ColorType::from_str(&value).map_err(|_| eyre!("unknown color {}", value))?;
+      }
+    }
+
+    if let Some(ascii_lines) = file.ascii_lines {
+      theme.ascii_lines = ascii_lines;
+    }
+    Ok(theme)
+  }
+}
+
+impl Default for Theme {
+  fn default() -> Self {
+    Theme::preset("default").unwrap()
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+  preset: Option<String>,
+  ascii_lines: Option<bool>,
+  #[serde(default)]
+  pieces: PiecesFile,
+  #[serde(default)]
+  colors: ColorsFile,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PiecesFile {
+  white_man: Option<char>,
+  black_man: Option<char>,
+  white_king: Option<char>,
+  black_king: Option<char>,
+  destination: Option<char>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColorsFile {
+  dark_square: Option<String>,
+  light_square: Option<String>,
+  white_piece: Option<String>,
+  black_piece: Option<String>,
+  cursor: Option<String>,
+  selected: Option<String>,
+  destination: Option<String>,
+  forced: Option<String>,
+  last_move: Option<String>,
+  hint: Option<String>,
+  label: Option<String>,
+}
@@ -1,120 +1,1203 @@
-use cursive::{Cursive, Printer, Vec2, View};
-use cursive::event::{Event, EventResult, Key};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use cursive::{CbSink, Cursive, Printer, Vec2, View};
+use cursive::event::{Event, EventResult, Key, MouseButton, MouseEvent};
 use cursive::theme::ColorStyle;
+use cursive::traits::*;
+use cursive::views::{Checkbox, Dialog, EditView, LinearLayout, Panel, RadioGroup, TextView};
+use eyre::{eyre, Result, WrapErr};
 use tui::symbols::line;
+use crate::engine::difficulty::Difficulty;
+use crate::engine::mcts::Mcts;
+use crate::engine::review::{review_game, GameReview, MoveQuality};
+use crate::engine::search::{SearchInfo, SearchLimits, Searcher};
+use crate::engine::time::{ClockTime, TimeManager};
 use crate::game::bitboard::Bitboard;
-use crate::game::MoveResult;
+use crate::game::{Checkerboard, MoveResult, PlayerColor};
+use crate::game::moves::{capturing_pieces, legal_moves, piece_destinations, Move};
+use crate::game::notation::square_number;
+use crate::game::pdn::PdnGame;
+
+use crate::interface::{CheckersGame, config_dir, GameResult, PlayerKind, PlayOptions};
+use crate::interface::analysis::{Analysis, AnalysisView};
+use crate::interface::clock::{format_time, TimeControl};
+use crate::interface::commands::{load_games, MatchScore};
+use crate::interface::movelist::MoveListView;
+use crate::interface::puzzles::{load_puzzles, Puzzle, PuzzleAttempt, PuzzleStats, PuzzleStep};
+use crate::interface::setup::Setup;
+
+/// Which move hints the board view draws; each can be switched off in the settings dialog.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Highlights {
+  /// Squares the selected piece can move to.
+  pub destinations: bool,
+  /// Pieces that are obliged to capture.
+  pub forced_captures: bool,
+  /// Squares the previous move passed through.
+  pub last_move: bool,
+}
+
+impl Default for Highlights {
+  fn default() -> Self {
+    Highlights { destinations: true, forced_captures: true, last_move: true }
+  }
+}
 
-use crate::interface::{CheckersGame, GameResult};
+/// Labels drawn in the corners of the board's cells.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Labels {
+  #[default]
+  None,
+  /// Files a-h along the bottom edge and ranks 1-8 along the left edge.
+  Coordinates,
+  /// The standard 1-32 number of every playable square.
+  Numbers,
+}
+
+/// A search started on the player's time from the position the computer expects them to reach.
+struct Ponder {
+  /// The expected position, with the computer to move.
+  position: (Checkerboard, PlayerColor),
+  receiver: Receiver<SearchInfo>,
+  stop: Arc<AtomicBool>,
+}
 
 pub struct CheckersView {
   game: CheckersGame,
+  options: PlayOptions,
+  highlights: Highlights,
+  labels: Labels,
+  /// Whether the board is drawn with Black at the bottom.
+  flipped: bool,
   focus: i32,
   start_square: Bitboard,
+  /// The square the left mouse button went down on, and whether it was already selected then.
+  pressed: Option<(Bitboard, bool)>,
   message: String,
+  thinking: Option<(Receiver<SearchInfo>, Arc<AtomicBool>)>,
+  /// The computer's search of the position after its predicted reply, run while the player thinks.
+  ponder: Option<Ponder>,
+  /// Wakes the event loop when the computer has found its move, so that the move is played, and
+  /// its clock stopped, at once rather than at the next refresh.
+  wake: Option<CbSink>,
+  /// The MCTS player's tree, kept from move to move within a game.
+  mcts: Arc<Mutex<Mcts>>,
+  /// The ply shown while reviewing an earlier position; `None` shows the live game.
+  review: Option<usize>,
+  /// The move count and shown ply last sent to the move list.
+  synced: Option<(usize, usize)>,
+  /// Results of this session's games for the player who had White in the first one.
+  score: MatchScore,
+  /// The color that player has in the current game; rematches swap it.
+  first_player: PlayerColor,
+  /// Whether the current game's result has been added to the score.
+  recorded: bool,
+  /// The position being edited while the board is in setup mode.
+  setup: Option<Setup>,
+  /// Whether the shown position is searched in the background for the analysis panel.
+  analysing: bool,
+  analysis: Option<Analysis>,
+  /// The suggested move and the position it is for.
+  hint: Option<(Checkerboard, Move)>,
+  /// The search for a hint and the position it started in.
+  hint_search: Option<(Checkerboard, Receiver<SearchInfo>, Arc<AtomicBool>)>,
+  /// Hints given in the current game.
+  hints_used: u32,
+  /// The review of the finished game's moves, once it is done.
+  report: Option<GameReview>,
+  report_search: Option<(Receiver<GameReview>, Arc<AtomicBool>)>,
+  /// The puzzle being solved in puzzle mode.
+  puzzle: Option<PuzzleAttempt>,
+  /// The check of the solver's last move, which answers with the attempt as it stands after it.
+  puzzle_check: Option<Receiver<(PuzzleAttempt, PuzzleStep)>>,
+  /// The puzzles to choose from, loaded when puzzle mode is first used.
+  puzzles: Vec<Puzzle>,
+  puzzle_stats: PuzzleStats,
 }
 
 impl CheckersView {
-  pub fn new() -> CheckersView {
-    CheckersView {
+  pub fn new(options: PlayOptions) -> CheckersView {
+    let mut view = CheckersView {
       game: CheckersGame::new(),
+      options: PlayOptions::default(),
+      highlights: Highlights::default(),
+      labels: Labels::default(),
+      flipped: false,
       focus: 0,
       start_square: Bitboard::new(),
-      message: String::from("Welcome to Checkers. White to move, have fun!") }
+      pressed: None,
+      message: String::new(),
+      thinking: None,
+      ponder: None,
+      wake: None,
+      mcts: Arc::new(Mutex::new(Mcts::new(options.mcts.clone(), options.playout))),
+      review: None,
+      synced: None,
+      score: MatchScore::default(),
+      first_player: PlayerColor::White,
+      recorded: false,
+      setup: None,
+      analysing: false,
+      analysis: None,
+      hint: None,
+      hint_search: None,
+      hints_used: 0,
+      report: None,
+      report_search: None,
+      puzzle: None,
+      puzzle_check: None,
+      puzzles: Vec::new(),
+      puzzle_stats: PuzzleStats::default(),
+    };
+    view.start_game(options);
+    view
   }
 
-  fn draw_playing(&self, printer: &Printer) {
-    draw_grid(printer);
+  pub fn with_wake(mut self, sink: CbSink) -> CheckersView {
+    self.wake = Some(sink);
+    self
+  }
+
+  pub fn options(&self) -> &PlayOptions {
+    &self.options
+  }
+
+  /// Starts a new game with `options`, keeping the display settings and the session score.
+  pub fn start_game(&mut self, options: PlayOptions) {
+    let mut game = match options.position {
+      Some((board, color)) => CheckersGame::from_position(board, color),
+      None => CheckersGame::new(),
+    };
+    if let Some(control) = options.time_control {
+      game.set_clock(control);
+    }
+    self.flipped = options.white != PlayerKind::Human && options.black == PlayerKind::Human;
+    self.mcts = Arc::new(Mutex::new(Mcts::new(options.mcts.clone(), options.playout)));
+    self.options = options;
+    let message = format!("Welcome to Checkers. {} to move, have fun!", game.on_move());
+    self.set_game(game, message);
+  }
+
+  /// Starts a new game with the same settings and the players' colors swapped.
+  pub fn rematch(&mut self) {
+    let mut options = self.options.clone();
+    std::mem::swap(&mut options.white, &mut options.black);
+    self.first_player = !self.first_player;
+    self.start_game(options);
+  }
+
+  /// Replaces the game, resetting everything tied to the previous one.
+  fn set_game(&mut self, game: CheckersGame, message: String) {
+    self.stop_computer();
+    self.stop_hint();
+    self.hint = None;
+    self.hints_used = 0;
+    self.stop_report();
+    self.report = None;
+    self.puzzle = None;
+    self.puzzle_check = None;
+    self.game = game;
+    self.focus = 0;
+    self.start_square = Bitboard::new();
+    self.pressed = None;
+    self.review = None;
+    self.synced = None;
+    self.recorded = false;
+    self.setup = None;
+    self.message = message;
+    self.check_game_over();
+    self.start_computer();
+  }
+
+  /// The result of the game and the session score, for the post-game dialog.
+  fn post_game_text(&self) -> String {
+    let result = self.game.result().map(|result| result.to_string()).unwrap_or_default();
+    let player = |color: PlayerColor| format!("{} ({})", self.options.player(color), color);
+    let points = |halves: u32| match (halves / 2, halves % 2) {
+      (0, 1) => String::from("½"),
+      (whole, 1) => format!("{}½", whole),
+      (whole, _) => whole.to_string(),
+    };
+    let score = &self.score;
+    format!("{}\n\nSession score: {} {} - {} {}", result,
+            player(self.first_player), points(2 * score.wins + score.draws),
+            points(2 * score.losses + score.draws), player(!self.first_player))
+  }
+
+  fn computer_to_move(&self) -> bool {
+    self.game.result().is_none() && self.options.player(self.game.on_move()) != PlayerKind::Human
+  }
+
+  /// Starts searching in the background if the computer is on move, or takes over its ponder search.
+  fn start_computer(&mut self) {
+    self.resolve_ponder();
+    if !self.computer_to_move() || self.thinking.is_some() {
+      return;
+    }
+    let board = *self.game.board();
+    let color = self.game.on_move();
+    let stop = Arc::new(AtomicBool::new(false));
+    self.thinking = Some((self.spawn_search(board, color, stop.clone()), stop));
+    self.message = format!("{} is thinking...", color);
+  }
+
+  /**
+  Once the player has finished their move, makes the ponder search the computer's search if the
+  player made the predicted move, and stops it otherwise. A search that has already finished hands
+  over its move at once.
+   */
+  fn resolve_ponder(&mut self) {
+    let position = (*self.game.board(), self.game.on_move());
+    let finished = match &self.ponder {
+      Some(ponder) => self.game.result().is_some() || position.1 == ponder.position.1,
+      None => false,
+    };
+    if !finished {
+      return;
+    }
+    let ponder = self.ponder.take().unwrap();
+    if self.game.result().is_none() && ponder.position == position {
+      self.thinking = Some((ponder.receiver, ponder.stop));
+      self.message = format!("{} is thinking...", position.1);
+    } else {
+      ponder.stop.store(true, Ordering::Relaxed);
+    }
+  }
+
+  /// After the computer's move, starts searching the position after the reply its search expects.
+  fn start_ponder(&mut self, info: &SearchInfo) {
+    let color = !self.game.on_move();
+    let reply = match info.pv.get(1) {
+      Some(reply) if self.options.ponder && self.game.result().is_none() && !self.computer_to_move() => reply,
+      _ => return,
+    };
+    let mut board = *self.game.board();
+    if !legal_moves(&board, !color).contains(reply) {
+      return;
+    }
+    board.apply(!color, reply);
+    let stop = Arc::new(AtomicBool::new(false));
+    let receiver = self.spawn_search(board, color, stop.clone());
+    self.ponder = Some(Ponder { position: (board, color), receiver, stop });
+  }
+
+  /// Starts the search for `color`'s move in `board` by the kind of player `color` is, on another thread.
+  fn spawn_search(&self, board: Checkerboard, color: PlayerColor, stop: Arc<AtomicBool>) -> Receiver<SearchInfo> {
+    let (sender, receiver) = channel();
+    let wake = self.wake.clone();
+    // hands over the result and wakes the event loop to pick it up
+    let finish = move |info: SearchInfo| {
+      let _ = sender.send(info);
+      if let Some(wake) = wake {
+        let _ = wake.send(Box::new(refresh));
+      }
+    };
+    let clock = self.game.clock().map(|clock| ClockTime {
+      remaining: clock.remaining(color),
+      increment: clock.control().increment,
+      moves_to_go: None,
+    });
+    if self.options.player(color) == PlayerKind::Mcts {
+      let mcts = self.mcts.clone();
+      let mut limits = self.options.mcts.clone();
+      if let Some(clock) = &clock {
+        let budget = TimeManager::new(clock).optimum();
+        limits.time = Some(limits.time.map_or(budget, |time| time.min(budget)));
+      }
+      thread::spawn(move || {
+        // a search being stopped may still hold the tree for a moment
+        let mut mcts = mcts.lock().unwrap();
+        mcts.set_stop(stop);
+        mcts.set_limits(limits);
+        finish(mcts.search(&board, color));
+      });
+    } else if let Some(difficulty) = self.options.difficulty {
+      let limits = SearchLimits { depth: Some(difficulty.depth()), clock, ..SearchLimits::default() };
+      let mut searcher = self.options.searcher(limits).with_stop(stop);
+      thread::spawn(move || {
+        finish(difficulty.choose_move(&board, color, &mut searcher, &mut rand::thread_rng()));
+      });
+    } else {
+      // on the clock the time manager decides how deep to go
+      let depth = if clock.is_some() { None } else { Some(self.options.depth) };
+      let mut searcher = self.options.searcher(SearchLimits { depth, clock, ..SearchLimits::default() }).with_stop(stop);
+      thread::spawn(move || {
+        finish(searcher.search(&board, color));
+      });
+    }
+    receiver
+  }
+
+  /// Plays the computer's move once its search has finished.
+  fn poll_computer(&mut self) {
+    let received = match &self.thinking {
+      Some((receiver, _)) => receiver.try_recv(),
+      None => return,
+    };
+    match received {
+      Ok(info) => {
+        self.thinking = None;
+        if let Some(mv) = info.best_move() {
+          let mv = mv.clone();
+          self.game.play_move(&mv);
+          self.message = format!("{} played {}. {} to move", !self.game.on_move(), mv, self.game.on_move());
+        }
+        self.check_game_over();
+        self.start_computer();
+        self.start_ponder(&info);
+      }
+      Err(TryRecvError::Empty) => {}
+      Err(TryRecvError::Disconnected) => self.thinking = None,
+    }
+  }
+
+  /// Starts a short search for the move to suggest to the player on move, if they have hints left.
+  fn request_hint(&mut self) {
+    let board = *self.game.board();
+    if self.hint_search.is_some() || self.hint.as_ref().is_some_and(|(hinted, _)| *hinted == board) {
+      return;
+    }
+    if self.options.hints.is_some_and(|hints| self.hints_used >= hints) {
+      self.message = String::from("No hints left in this game");
+      return;
+    }
+    let (sender, receiver) = channel();
+    let color = self.game.on_move();
+    let limits = SearchLimits { depth: Some(self.options.depth), time: Some(HINT_TIME), ..SearchLimits::default() };
+    let mut searcher = self.options.searcher(limits);
+    let stop = searcher.stop_flag();
+    thread::spawn(move || {
+      let _ = sender.send(searcher.search(&board, color));
+    });
+    self.hint_search = Some((board, receiver, stop));
+    self.message = String::from("Looking for a hint...");
+  }
+
+  /// Shows the hint once its search has finished, unless a move was made in the meantime.
+  fn poll_hint(&mut self) {
+    let received = match &self.hint_search {
+      Some((_, receiver, _)) => receiver.try_recv(),
+      None => return,
+    };
+    match received {
+      Ok(info) => {
+        let (board, _, _) = self.hint_search.take().unwrap();
+        match info.best_move() {
+          Some(mv) if board == *self.game.board() => {
+            self.hints_used += 1;
+            self.message = match self.options.hints {
+              Some(hints) => format!("Hint: {} ({} of {} hints used)", mv, self.hints_used, hints),
+              None => format!("Hint: {}", mv),
+            };
+            self.hint = Some((board, mv.clone()));
+          }
+          _ => {}
+        }
+      }
+      Err(TryRecvError::Empty) => {}
+      Err(TryRecvError::Disconnected) => self.hint_search = None,
+    }
+  }
+
+  fn stop_hint(&mut self) {
+    if let Some((_, _, stop)) = self.hint_search.take() {
+      stop.store(true, Ordering::Relaxed);
+    }
+  }
+
+  /**
+  Starts reviewing every move of the game in the background, for the report shown by
+  `report_dialog`. Returns a callback that shows the report right away if it is already done.
+   */
+  pub fn start_report(&mut self) -> EventResult {
+    if self.report.is_some() {
+      return EventResult::with_cb(report_dialog);
+    }
+    if self.report_search.is_some() {
+      return EventResult::Ignored;
+    }
+    let (sender, receiver) = channel();
+    let start = self.game.start_position();
+    let moves = self.game.history().to_vec();
+    let mut searcher = self.options.searcher(SearchLimits::depth(self.options.depth));
+    let stop = searcher.stop_flag();
+    thread::spawn(move || {
+      let _ = sender.send(review_game(start, &moves, &mut searcher));
+    });
+    self.report_search = Some((receiver, stop));
+    self.message = String::from("Reviewing the moves...");
+    EventResult::Ignored
+  }
+
+  /// Keeps the finished review and returns a callback showing it.
+  fn poll_report(&mut self) -> EventResult {
+    let received = match &self.report_search {
+      Some((receiver, _)) => receiver.try_recv(),
+      None => return EventResult::Ignored,
+    };
+    match received {
+      Ok(report) => {
+        self.report_search = None;
+        self.report = Some(report);
+        // the move list marks the reviewed moves
+        self.synced = None;
+        self.message = String::from("Review done, Enter for more options");
+        EventResult::with_cb(report_dialog)
+      }
+      Err(TryRecvError::Empty) => EventResult::Ignored,
+      Err(TryRecvError::Disconnected) => {
+        self.report_search = None;
+        EventResult::Ignored
+      }
+    }
+  }
+
+  fn stop_report(&mut self) {
+    if let Some((_, stop)) = self.report_search.take() {
+      stop.store(true, Ordering::Relaxed);
+    }
+  }
+
+  /// Each player's accuracy followed by the moves that were not good, with the better move.
+  fn report_text(&self) -> String {
+    let report = match &self.report {
+      Some(report) => report,
+      None => return String::new(),
+    };
+    let mut text = format!("{}\n{}\n", report.summary(PlayerColor::White), report.summary(PlayerColor::Black));
+    let black_first = self.game.start_position().1 == PlayerColor::Black;
+    for (ply, mv) in report.moves.iter().enumerate() {
+      let quality = mv.quality();
+      if quality == MoveQuality::Good {
+        continue;
+      }
+      let slot = ply + black_first as usize;
+      let dots = if slot.is_multiple_of(2) { "." } else { "..." };
+      text.push_str(&format!("\n{}{} {}{}  {}, best was {}", slot / 2 + 1, dots, mv.played, quality.symbol(),
+                             quality, mv.best));
+    }
+    text
+  }
+
+  /// Whether there is a game under way that going on to a puzzle would leave: one with moves that is not over.
+  pub fn game_in_progress(&self) -> bool {
+    self.puzzle.is_none() && self.game.result().is_none() && !self.game.history().is_empty()
+  }
+
+  /**
+  Sets up the next puzzle: the untried one rated closest to the player. Puzzles and the player's
+  stats are loaded the first time. Both sides are played by hand, the opponent's replies coming
+  from the solution.
+   */
+  pub fn next_puzzle(&mut self) -> Result<()> {
+    if self.puzzles.is_empty() {
+      self.puzzles = load_puzzles(self.options.puzzle_file.as_deref())?;
+      if let Some(path) = PuzzleStats::path() {
+        self.puzzle_stats = PuzzleStats::load(&path)?;
+      }
+    }
+    let puzzle = self.puzzle_stats.next_puzzle(&self.puzzles).ok_or_else(|| eyre!("there are no puzzles"))?.clone();
+    self.options.white = PlayerKind::Human;
+    self.options.black = PlayerKind::Human;
+    self.options.time_control = None;
+    self.flipped = puzzle.color == PlayerColor::Black;
+    let message = format!("Puzzle ({}, rated {}): {} to play and win", puzzle.theme, puzzle.rating, puzzle.color);
+    self.set_game(CheckersGame::from_position(puzzle.board, puzzle.color), message);
+    self.puzzle = Some(PuzzleAttempt::new(puzzle));
+    Ok(())
+  }
+
+  /**
+  Starts checking the move just completed against the puzzle's solution. A move other than the
+  solution's is searched, so the check runs on another thread and `poll_puzzle` picks it up.
+   */
+  fn check_puzzle(&mut self) {
+    let mut attempt = match &self.puzzle {
+      Some(attempt) if attempt.outcome.is_none() && attempt.puzzle.color != self.game.on_move() => attempt.clone(),
+      _ => return,
+    };
+    let ply = self.game.history().len() - 1;
+    let (board, _) = self.game.position_at(ply);
+    let played = self.game.history()[ply].clone();
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+      let limits = SearchLimits { depth: Some(PUZZLE_CHECK_DEPTH), time: Some(PUZZLE_CHECK_TIME), ..SearchLimits::default() };
+      let step = attempt.check(&board, &played, &mut Searcher::new(limits));
+      let _ = sender.send((attempt, step));
+    });
+    self.puzzle_check = Some(receiver);
+    self.message = String::from("Checking your move...");
+  }
+
+  /// Takes in the result of the check of the solver's move, answering the move if it was right.
+  fn poll_puzzle(&mut self) {
+    let received = match &self.puzzle_check {
+      Some(receiver) => receiver.try_recv(),
+      None => return,
+    };
+    let (attempt, step) = match received {
+      Ok(checked) => checked,
+      Err(TryRecvError::Empty) => return,
+      Err(TryRecvError::Disconnected) => {
+        self.puzzle_check = None;
+        return;
+      }
+    };
+    self.puzzle_check = None;
+    let puzzle = attempt.puzzle.clone();
+    self.puzzle = Some(attempt);
+    match step {
+      PuzzleStep::Correct { reply } => {
+        self.game.play_move(&reply);
+        self.message = format!("Correct! {} answered {}, your move", !puzzle.color, reply);
+      }
+      PuzzleStep::Solved => self.message = format!("Solved! {}, p for the next puzzle", self.record_puzzle(&puzzle, true)),
+      PuzzleStep::Wrong { solution } => {
+        self.message = format!("{} was the move. {}", solution, self.record_puzzle(&puzzle, false));
+      }
+    }
+  }
+
+  /// Adds the attempt to the player's stats and saves them; describes the new rating.
+  fn record_puzzle(&mut self, puzzle: &Puzzle, solved: bool) -> String {
+    let before = self.puzzle_stats.rating;
+    self.puzzle_stats.record(puzzle, solved);
+    let rating = self.puzzle_stats.rating;
+    match PuzzleStats::path().map(|path| self.puzzle_stats.save(&path)) {
+      Some(Err(error)) => format!("Rating {} ({:+}), not saved: {}", rating, rating - before, error),
+      _ => format!("Rating {} ({:+})", rating, rating - before),
+    }
+  }
 
-    // pieces
+  fn draw_clocks(&self, printer: &Printer) {
+    if self.puzzle.is_some() {
+      let stats = &self.puzzle_stats;
+      printer.print((0, 34), &format!("Puzzle rating {}, streak {}, best {}", stats.rating, stats.streak, stats.best_streak));
+    } else if let Some(clock) = self.game.clock() {
+      let side = |color: PlayerColor| {
+        let marker = if clock.running() == Some(color) { '>' } else { ' ' };
+        format!("{}{} {}", marker, color, format_time(clock.remaining(color)))
+      };
+      printer.print((0, 34), &format!("{}   {}", side(PlayerColor::White), side(PlayerColor::Black)));
+    }
+  }
+
+  pub fn highlights(&self) -> Highlights {
+    self.highlights
+  }
+
+  pub fn set_highlights(&mut self, highlights: Highlights) {
+    self.highlights = highlights;
+  }
+
+  pub fn labels(&self) -> Labels {
+    self.labels
+  }
+
+  pub fn set_labels(&mut self, labels: Labels) {
+    self.labels = labels;
+  }
+
+  /// Draws the labels chosen in the settings into the corners of the cell at `pos`.
+  fn draw_label(&self, printer: &Printer, sq: i32, pos: Vec2) {
+    let (bottom_rank, left_file) = if self.flipped { (7, 7) } else { (0, 0) };
+    let (rank, file) = (sq >> 3, sq & 7);
+    match self.labels {
+      Labels::None => {}
+      Labels::Coordinates => {
+        if rank == bottom_rank {
+          printer.print(pos + (2, 1), &char::from(b'a' + file as u8).to_string());
+        }
+        if file == left_file {
+          printer.print(pos - (2, 1), &(rank + 1).to_string());
+        }
+      }
+      Labels::Numbers => {
+        if let Some(number) = square_number(Bitboard::from(1 << sq)) {
+          printer.print(pos - (2, 1), &number.to_string());
+        }
+      }
+    }
+  }
+
+  /// Draws the pieces and move hints; `interactive` adds the cursor and selection.
+  fn draw_pieces(&self, printer: &Printer, interactive: bool) {
+    let (board, color) = self.shown_position();
+    let board = &board;
+    let interactive = interactive && self.review.is_none();
+    let human_to_move = interactive && self.thinking.is_none() && self.puzzle_check.is_none() && self.setup.is_none();
+    let destinations = if human_to_move && self.highlights.destinations && self.start_square.is_not_empty() {
+      piece_destinations(board, color, self.start_square)
+    } else {
+      Bitboard::new()
+    };
+    let forced = if human_to_move && self.highlights.forced_captures {
+      capturing_pieces(board, color)
+    } else {
+      Bitboard::new()
+    };
+    let mut hint = Bitboard::new();
+    if let Some((hinted, mv)) = &self.hint {
+      if human_to_move && *hinted == *self.game.board() {
+        for &square in mv.path() {
+          hint |= square;
+        }
+      }
+    }
+    let mut last_move = Bitboard::new();
+    if self.highlights.last_move && self.setup.is_none() {
+      let shown = &self.game.history()[..self.shown_ply()];
+      for &square in shown.last().map(|mv| mv.path()).unwrap_or_default() {
+        last_move |= square;
+      }
+    }
+
+    let theme = &self.options.theme;
+    let colors = &theme.colors;
     let mut chbuf = [0; 4];
-    for (sq, ch) in (0..).zip(self.game.board.raw_string().chars()) {
-      let color_style = if sq == self.focus {
-        ColorStyle::highlight()
-      } else if sq == self.start_square.index() {
-        ColorStyle::highlight_inactive()
+    for sq in 0..64 {
+      let square = Bitboard::from(1 << sq);
+      let pos = term_pos(sq, self.flipped).unwrap();
+      let back = if interactive && sq == self.focus {
+        colors.cursor
+      } else if interactive && square == self.start_square {
+        colors.selected
+      } else if (hint & square).is_not_empty() {
+        colors.hint
+      } else if (last_move & square).is_not_empty() {
+        colors.last_move
+      } else if ((sq >> 3) ^ sq) & 1 == 0 {
+        colors.dark_square
       } else {
-        ColorStyle::inherit_parent()
+        colors.light_square
       };
-      printer.with_color(color_style, |printer|
-          printer.print(term_pos(sq).unwrap(), ch.encode_utf8(&mut chbuf)),
-      );
+      printer.with_color(ColorStyle::new(colors.label, back), |printer| {
+        for dy in 0..3 {
+          printer.print(pos + (0, dy) - (2, 1), "     ");
+        }
+        self.draw_label(printer, sq, pos);
+      });
+
+      let glyph = if (destinations & square).is_not_empty() {
+        Some((theme.pieces.destination, colors.destination))
+      } else {
+        theme.pieces.glyph(board, square).map(|(glyph, owner)| {
+          let front = if (forced & square).is_not_empty() {
+            colors.forced
+          } else if owner == PlayerColor::White {
+            colors.white_piece
+          } else {
+            colors.black_piece
+          };
+          (glyph, front)
+        })
+      };
+      if let Some((glyph, front)) = glyph {
+        printer.with_color(ColorStyle::new(front, back), |printer|
+            printer.print(pos, glyph.encode_utf8(&mut chbuf)),
+        );
+      }
     }
-    printer.print((0, 33), &self.message);
+  }
+
+  fn draw_message(&self, printer: &Printer) {
+    match self.review {
+      Some(ply) => printer.print((0, 33), &format!("Reviewing ply {} of {}, End returns to the game",
+                                                   ply, self.game.history().len())),
+      None => printer.print((0, 33), &self.message),
+    }
+  }
+
+  fn draw_playing(&self, printer: &Printer) {
+    draw_grid(printer, self.options.theme.ascii_lines);
+    self.draw_pieces(printer, true);
+    self.draw_message(printer);
+    self.draw_clocks(printer);
   }
 
   fn draw_finished(&self, printer: &Printer) {
-    draw_grid(printer);
+    draw_grid(printer, self.options.theme.ascii_lines);
+    self.draw_pieces(printer, false);
+    self.draw_message(printer);
+    self.draw_clocks(printer);
+  }
 
-    // pieces
-    let mut chbuf = [0; 4];
-    for (sq, ch) in (0..).zip(self.game.board.raw_string().chars()) {
-      printer.print(term_pos(sq).unwrap(), ch.encode_utf8(&mut chbuf));
+  fn draw_setup(&self, printer: &Printer) {
+    draw_grid(printer, self.options.theme.ascii_lines);
+    self.draw_pieces(printer, true);
+    self.draw_message(printer);
+    printer.print((0, 34), "w b W B place, Space clears, t side, Enter done");
+  }
+
+  /// The position on the board: the one being edited, the one under review or the live one.
+  fn shown_position(&self) -> (Checkerboard, PlayerColor) {
+    match (&self.setup, self.review) {
+      (Some(setup), _) => (setup.board, setup.on_move),
+      (None, Some(ply)) => self.game.position_at(ply),
+      (None, None) => (*self.game.board(), self.game.on_move()),
+    }
+  }
+
+  /// The number of plies played in the position on the board.
+  fn shown_ply(&self) -> usize {
+    self.review.unwrap_or(self.game.history().len())
+  }
+
+  /// Shows the position after `ply` moves, or the live game once `ply` reaches the end.
+  fn review_ply(&mut self, ply: usize) {
+    self.review = if ply < self.game.history().len() { Some(ply) } else { None };
+  }
+
+  /**
+  Handles the keys that change only what is shown, not the game: flipping the board, and the keys
+  and mouse wheel that step through earlier positions.
+   */
+  fn on_event_display(&mut self, event: &Event) -> EventResult {
+    let shown = self.shown_ply();
+    match event {
+      Event::Char('f') => self.flipped = !self.flipped,
+      Event::Key(Key::PageUp) => self.review_ply(shown.saturating_sub(1)),
+      Event::Key(Key::PageDown) => self.review_ply(shown + 1),
+      Event::Key(Key::Home) => self.review_ply(0),
+      Event::Key(Key::End) => self.review = None,
+      Event::Key(Key::Esc) if self.review.is_some() => self.review = None,
+      Event::Mouse { event: MouseEvent::WheelUp, .. } => self.review_ply(shown.saturating_sub(1)),
+      Event::Mouse { event: MouseEvent::WheelDown, .. } => self.review_ply(shown + 1),
+      _ => return EventResult::Ignored,
     }
-    printer.print((0, 33), &self.message);
+    EventResult::Consumed(None)
+  }
+
+  pub fn analysing(&self) -> bool {
+    self.analysing
+  }
+
+  pub fn set_analysing(&mut self, analysing: bool) {
+    self.analysing = analysing;
+    if !analysing {
+      self.analysis = None;
+    }
+  }
+
+  /**
+  Restarts the analysis whenever the shown position differs from the one being searched, which
+  cancels the old search, and passes any new results on to the analysis panel.
+   */
+  fn update_analysis(&mut self) -> EventResult {
+    if !self.analysing {
+      return EventResult::Ignored;
+    }
+    let (board, color) = self.shown_position();
+    let restarted = self.analysis.as_ref().is_none_or(|analysis| analysis.position() != (board, color));
+    if restarted {
+      self.analysis = Some(Analysis::start(board, color, self.options.analysis_lines, self.options.searcher(SearchLimits::default())));
+    }
+    let analysis = self.analysis.as_mut().unwrap();
+    if !analysis.poll() && !restarted {
+      return EventResult::Ignored;
+    }
+    let lines = analysis.lines().to_vec();
+    let stuck = legal_moves(&board, color).is_empty();
+    EventResult::with_cb(move |s| {
+      s.call_on_name("analysis", |view: &mut AnalysisView| {
+        if stuck {
+          view.set_note(&format!("{} has no legal moves", color));
+        } else {
+          view.set_lines(color, &lines);
+        }
+      });
+    })
+  }
+
+  /// Sends the moves and the shown ply to the move list if they changed since the last update.
+  fn sync_move_list(&mut self) -> EventResult {
+    let state = (self.game.history().len(), self.shown_ply());
+    if self.synced == Some(state) {
+      return EventResult::Ignored;
+    }
+    self.synced = Some(state);
+    let mut moves: Vec<String> = self.game.history().iter().map(Move::to_string).collect();
+    if let Some(report) = &self.report {
+      for (mv, review) in moves.iter_mut().zip(&report.moves) {
+        mv.push_str(review.quality().symbol());
+      }
+    }
+    let black_first = self.game.start_position().1 == PlayerColor::Black;
+    EventResult::with_cb(move |s| {
+      s.call_on_name("moves", |list: &mut MoveListView| list.set_moves(moves.clone(), black_first, state.1));
+    })
+  }
+
+  /**
+  Periodic work: picks up the computer's move, checks the clocks and brings the move list and the
+  analysis up to date. Called from a global callback so that it keeps running while a dialog is
+  open.
+   */
+  pub fn refresh(&mut self) -> EventResult {
+    if self.game.result().is_none() && self.setup.is_none() {
+      self.poll_computer();
+      self.poll_hint();
+      self.check_game_over();
+    }
+    // the last move of a solution may end the game
+    self.poll_puzzle();
+    let mut result = self.poll_report();
+    result = result.and(self.sync_move_list()).and(self.update_analysis());
+    // puzzles are not part of the session score
+    if let (Some(outcome), false) = (self.game.result(), self.recorded || self.puzzle.is_some()) {
+      self.recorded = true;
+      match outcome {
+        GameResult::Victory(color) if color == self.first_player => self.score.wins += 1,
+        GameResult::Victory(_) => self.score.losses += 1,
+        GameResult::Draw => self.score.draws += 1,
+      }
+      result = result.and(EventResult::with_cb(post_game));
+    }
+    result
   }
 
   fn on_event_playing(&mut self, event: Event) -> EventResult {
+    if self.thinking.is_some() || self.puzzle_check.is_some() || self.review.is_some() {
+      return EventResult::Ignored;
+    }
     match event {
+      Event::Char('h') => self.request_hint(),
       Event::Key(key) => match key {
         Key::Enter => self.square_selected(Bitboard::from(1 << self.focus)),
-        Key::Up => if self.focus < 56 { self.focus += 8; },
-        Key::Down => if self.focus > 7 { self.focus -= 8; },
-        Key::Left => if self.focus & 7 > 0 { self.focus -= 1; },
-        Key::Right => if self.focus & 7 < 7 { self.focus += 1; },
+        Key::Up => self.move_focus(0, 1),
+        Key::Down => self.move_focus(0, -1),
+        Key::Left => self.move_focus(-1, 0),
+        Key::Right => self.move_focus(1, 0),
         _ => return EventResult::Ignored
       }
+      Event::Mouse { offset, position, event } => {
+        let square = match position.checked_sub(offset).and_then(|pos| square_at(pos, self.flipped)) {
+          Some(square) => square,
+          None => {
+            self.pressed = None;
+            return EventResult::Ignored;
+          }
+        };
+        return self.on_mouse(square, event);
+      }
       _ => return EventResult::Ignored
     }
     EventResult::Consumed(None)
   }
 
-  fn on_event_finished(&mut self, _: Event) -> EventResult {
-    EventResult::Ignored
+  /// Moves the cursor by the given number of columns and rows as seen on the screen.
+  fn move_focus(&mut self, right: i32, up: i32) {
+    let (right, up) = if self.flipped { (-right, -up) } else { (right, up) };
+    let file = (self.focus & 7) + right;
+    let rank = (self.focus >> 3) + up;
+    if (0..8).contains(&file) && (0..8).contains(&rank) {
+      self.focus = rank * 8 + file;
+    }
+  }
+
+  /**
+  Handles a mouse event over board square `sq`. Clicking a piece and then a destination plays the
+  step, as does dragging the piece there; clicking the selected piece again deselects it.
+   */
+  fn on_mouse(&mut self, sq: i32, event: MouseEvent) -> EventResult {
+    let square = Bitboard::from(1 << sq);
+    match event {
+      MouseEvent::Press(MouseButton::Left) => {
+        self.focus = sq;
+        if square == self.start_square {
+          self.pressed = Some((square, true));
+        } else {
+          self.square_selected(square);
+          self.pressed = Some((square, false));
+        }
+      }
+      MouseEvent::Hold(MouseButton::Left) => self.focus = sq,
+      MouseEvent::Release(_) => {
+        self.focus = sq;
+        match self.pressed.take() {
+          Some((from, true)) if from == square => self.square_selected(square),
+          Some((from, false)) if from == square => {}
+          Some((from, _)) if from == self.start_square => self.square_selected(square),
+          _ => {}
+        }
+      }
+      _ => return EventResult::Ignored,
+    }
+    EventResult::Consumed(None)
+  }
+
+  /// Switches to setup mode, starting from the position on the board.
+  pub fn start_setup(&mut self) {
+    self.stop_computer();
+    let (board, color) = self.shown_position();
+    self.setup = Some(Setup::new(&board, color));
+    self.review = None;
+    self.start_square = Bitboard::new();
+    self.pressed = None;
+    self.focus = self.focus.max(0);
+    self.message = format!("Setup: {} to move", color);
+  }
+
+  /// Leaves setup mode without changing the game.
+  fn cancel_setup(&mut self) {
+    self.setup = None;
+    self.message = format!("{} to move", self.game.on_move());
+    self.start_computer();
+  }
+
+  /**
+  Starts a game from the position being set up. For analysis both sides are played by hand and
+  there is no clock, so moves can be tried out freely.
+   */
+  pub fn finish_setup(&mut self, analysis: bool) {
+    let position = match self.setup.as_ref().map(Setup::finish) {
+      Some(Ok(position)) => position,
+      Some(Err(error)) => {
+        self.message = error.to_string();
+        return;
+      }
+      None => return,
+    };
+    let mut options = self.options.clone();
+    options.position = Some(position);
+    if analysis {
+      options.white = PlayerKind::Human;
+      options.black = PlayerKind::Human;
+      options.time_control = None;
+    }
+    let flipped = self.flipped;
+    self.start_game(options);
+    self.flipped = flipped;
+  }
+
+  /**
+  Handles the editing keys: the letters of the pieces put one on the cursor's square, Space, Delete
+  or Backspace empty it, `t` changes the side to move, `c` clears the board and `r` restores the
+  starting position. Enter offers to play or analyse the position and Esc abandons it.
+   */
+  fn on_event_setup(&mut self, event: Event) -> EventResult {
+    use PlayerColor::{Black, White};
+    let setup = match &mut self.setup {
+      Some(setup) => setup,
+      None => return EventResult::Ignored,
+    };
+    let piece = match event {
+      Event::Char('w') => Some((White, false)),
+      Event::Char('b') => Some((Black, false)),
+      Event::Char('W') => Some((White, true)),
+      Event::Char('B') => Some((Black, true)),
+      Event::Char(' ') | Event::Key(Key::Del) | Event::Key(Key::Backspace) => None,
+      Event::Char('t') => {
+        setup.on_move = !setup.on_move;
+        self.message = format!("Setup: {} to move", setup.on_move);
+        return EventResult::Consumed(None);
+      }
+      Event::Char('c') => {
+        setup.clear();
+        return EventResult::Consumed(None);
+      }
+      Event::Char('r') => {
+        *setup = Setup::new(&Checkerboard::new(), White);
+        self.message = format!("Setup: {} to move", White);
+        return EventResult::Consumed(None);
+      }
+      Event::Char('f') => {
+        self.flipped = !self.flipped;
+        return EventResult::Consumed(None);
+      }
+      Event::Key(Key::Enter) => return EventResult::with_cb(setup_done),
+      Event::Key(Key::Esc) => {
+        self.cancel_setup();
+        return EventResult::Consumed(None);
+      }
+      Event::Key(Key::Up) => return self.setup_focus(0, 1),
+      Event::Key(Key::Down) => return self.setup_focus(0, -1),
+      Event::Key(Key::Left) => return self.setup_focus(-1, 0),
+      Event::Key(Key::Right) => return self.setup_focus(1, 0),
+      Event::Mouse { offset, position, event: MouseEvent::Press(MouseButton::Left) } => {
+        return match position.checked_sub(offset).and_then(|pos| square_at(pos, self.flipped)) {
+          Some(sq) => {
+            self.focus = sq;
+            EventResult::Consumed(None)
+          }
+          None => EventResult::Ignored,
+        };
+      }
+      _ => return EventResult::Ignored,
+    };
+    self.message = match setup.place(Bitboard::from(1 << self.focus), piece) {
+      Ok(()) => format!("Setup: {} to move", setup.on_move),
+      Err(error) => error.to_string(),
+    };
+    EventResult::Consumed(None)
+  }
+
+  fn setup_focus(&mut self, right: i32, up: i32) -> EventResult {
+    self.move_focus(right, up);
+    EventResult::Consumed(None)
+  }
+
+  fn on_event_finished(&mut self, event: Event) -> EventResult {
+    match event {
+      Event::Key(Key::Enter) => EventResult::with_cb(post_game),
+      _ => EventResult::Ignored,
+    }
   }
 
   fn square_selected(&mut self, square: Bitboard) {
     if self.start_square.is_empty() {
-      if (self.game.board.pieces(self.game.on_move) & square).is_not_empty() {
+      if (self.game.board().pieces(self.game.on_move()) & square).is_not_empty() {
         self.start_square = square;
       }
     } else if square == self.start_square {
       self.start_square = Bitboard::new();
+    } else if self.game.pending_capture().is_none() && (self.game.board().pieces(self.game.on_move()) & square).is_not_empty() {
+      self.start_square = square;
     } else {
-      let result = self.game.board.make_move(self.game.on_move, self.start_square, square);
+      let mover = self.game.on_move();
+      let result = self.game.play_step(self.start_square, square);
       match result {
         MoveResult::Valid(color) => {
-          if color == self.game.on_move {
+          if color == mover {
             self.start_square = square;
             self.message = String::from("Continue capture sequence");
           } else {
             self.start_square = Bitboard::new();
-            self.game.on_move = color;
             self.message = format!("{} to move", color);
           }
           self.check_game_over();
+          self.check_puzzle();
+          self.start_computer();
         }
-        MoveResult::Invalid => self.message = String::from("Invalid move")
+        MoveResult::Invalid(error) => self.message = error.to_string()
       }
     }
   }
 
   fn check_game_over(&mut self) {
-    self.game.result = self.game.game_over();
-    if let Some(result) = self.game.result {
+    if let Some(result) = self.game.check_clock() {
       self.message = match result {
         GameResult::Victory(color) => format!("{} won!", color),
         GameResult::Draw => String::from("It was a draw!"),
       };
       self.focus = -1;
       self.start_square = Bitboard::new();
+      self.stop_computer();
+    }
+  }
+
+  fn stop_computer(&mut self) {
+    if let Some((_, stop)) = self.thinking.take() {
+      stop.store(true, Ordering::Relaxed);
+    }
+    if let Some(ponder) = self.ponder.take() {
+      ponder.stop.store(true, Ordering::Relaxed);
+    }
+  }
+
+  /// The game as PDN, with the `White` and `Black` tags naming who plays each side.
+  fn to_pdn(&self) -> PdnGame {
+    let mut pdn = self.game.to_pdn();
+    pdn.set_tag("White", self.options.white.to_string());
+    pdn.set_tag("Black", self.options.black.to_string());
+    pdn
+  }
+
+  pub fn save(&mut self, path: &Path) -> Result<()> {
+    fs::write(path, self.to_pdn().to_string()).wrap_err_with(|| format!("could not write {}", path.display()))?;
+    self.message = format!("Game saved to {}", path.display());
+    Ok(())
+  }
+
+  /// Saves the game with the review's glyphs, comments and accuracy tags.
+  pub fn save_annotated(&mut self, path: &Path) -> Result<()> {
+    let mut pdn = self.to_pdn();
+    if let Some(report) = &self.report {
+      report.annotate(&mut pdn);
+    }
+    fs::write(path, pdn.to_string()).wrap_err_with(|| format!("could not write {}", path.display()))?;
+    self.message = format!("Annotated game saved to {}", path.display());
+    Ok(())
+  }
+
+  /**
+  Replaces the game with the first game of the PDN file at `path`. `White` and `Black` tags of
+  `Human`, `Computer` or `MCTS` decide who plays each side; otherwise the current players stay.
+   */
+  pub fn load(&mut self, path: &Path) -> Result<()> {
+    let games = load_games(path)?;
+    let pdn = games.first().ok_or_else(|| eyre!("{} contains no games", path.display()))?;
+    let game = CheckersGame::from_pdn(pdn).wrap_err_with(|| format!("could not replay {}", path.display()))?;
+    for (tag, player) in [("White", &mut self.options.white), ("Black", &mut self.options.black)] {
+      match pdn.tag(tag) {
+        Some("Human") => *player = PlayerKind::Human,
+        Some("Computer") => *player = PlayerKind::Computer,
+        Some("MCTS") => *player = PlayerKind::Mcts,
+        _ => {}
+      }
+    }
+
+    let message = format!("Game loaded. {} to move", game.on_move());
+    self.set_game(game, message);
+    // a finished game that was loaded does not count towards the session score
+    self.recorded = self.game.result().is_some();
+    Ok(())
+  }
+
+  /**
+  Saves an unfinished game to the autosave file so it can be resumed on the next start, and removes
+  the file once a game has finished. A game with no moves, or a puzzle, leaves an earlier autosave
+  in place, so the game left for a puzzle can still be resumed.
+   */
+  pub fn autosave(&self) -> Result<()> {
+    let path = match autosave_path() {
+      Some(path) if self.puzzle.is_none() => path,
+      _ => return Ok(()),
+    };
+    if self.game.result().is_some() {
+      if path.is_file() {
+        fs::remove_file(&path).wrap_err_with(|| format!("could not remove {}", path.display()))?;
+      }
+    } else if !self.game.history().is_empty() {
+      if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).wrap_err_with(|| format!("could not create {}", dir.display()))?;
+      }
+      fs::write(&path, self.to_pdn().to_string()).wrap_err_with(|| format!("could not write {}", path.display()))?;
+    }
+    Ok(())
+  }
+}
+
+impl Default for CheckersView {
+  fn default() -> Self {
+    CheckersView::new(PlayOptions::default())
+  }
+}
+
+impl Drop for CheckersView {
+  fn drop(&mut self) {
+    self.stop_hint();
+    self.stop_report();
+    if let Some((_, stop)) = &self.thinking {
+      stop.store(true, Ordering::Relaxed);
+    }
+    if let Some(ponder) = &self.ponder {
+      ponder.stop.store(true, Ordering::Relaxed);
     }
   }
 }
 
 impl View for CheckersView {
   fn draw(&self, printer: &Printer) {
-    if self.game.result.is_some() {
+    if self.setup.is_some() {
+      self.draw_setup(printer);
+    } else if self.game.result().is_some() {
       self.draw_finished(printer);
     } else {
       self.draw_playing(printer);
@@ -122,54 +1205,462 @@ impl View for CheckersView {
   }
 
   fn required_size(&mut self, _: Vec2) -> Vec2 {
-    Vec2::new(49, 34)
+    Vec2::new(49, 35)
   }
 
   fn on_event(&mut self, event: Event) -> EventResult {
-    if self.game.result.is_some() {
-      self.on_event_finished(event)
-    } else {
-      self.on_event_playing(event)
+    if self.setup.is_some() {
+      return self.on_event_setup(event);
+    }
+    if event == Event::Char('e') {
+      self.start_setup();
+      return EventResult::Consumed(None);
+    }
+    let result = match self.on_event_display(&event) {
+      EventResult::Ignored if self.game.result().is_some() => self.on_event_finished(event),
+      EventResult::Ignored => self.on_event_playing(event),
+      consumed => consumed,
+    };
+    // events the view ignores must stay ignored so the global shortcuts still see them
+    match result {
+      EventResult::Ignored => result,
+      result => result.and(self.sync_move_list()),
     }
   }
 }
 
-fn draw_grid(printer: &Printer) {
-// grid
+const ASCII_LINES: line::Set = line::Set {
+  vertical: "|",
+  horizontal: "-",
+  top_right: "+",
+  top_left: "+",
+  bottom_right: "+",
+  bottom_left: "+",
+  vertical_left: "+",
+  vertical_right: "+",
+  horizontal_down: "+",
+  horizontal_up: "+",
+  cross: "+",
+};
+
+fn draw_grid(printer: &Printer, ascii: bool) {
+  let line = if ascii { ASCII_LINES } else { line::NORMAL };
   for i in (0..=48).step_by(6) {
-    printer.print_vline((i, 1), 31, line::VERTICAL);
+    printer.print_vline((i, 1), 31, line.vertical);
   }
   for i in (0..=32).step_by(4) {
-    printer.print((0, i), line::VERTICAL_RIGHT);
-    printer.print_hline((1, i), 47, line::HORIZONTAL);
-    printer.print((48, i), line::VERTICAL_LEFT);
+    printer.print((0, i), line.vertical_right);
+    printer.print_hline((1, i), 47, line.horizontal);
+    printer.print((48, i), line.vertical_left);
   }
   for i in (0..=48).step_by(6) {
-    printer.print((i, 0), line::HORIZONTAL_DOWN);
-    printer.print((i, 32), line::HORIZONTAL_UP);
+    printer.print((i, 0), line.horizontal_down);
+    printer.print((i, 32), line.horizontal_up);
   }
   for col in (6..=42).step_by(6) {
     for row in (4..=28).step_by(4) {
-      printer.print((col, row), line::CROSS);
+      printer.print((col, row), line.cross);
     }
   }
-  printer.print((0, 0), line::TOP_LEFT);
-  printer.print((48, 0), line::TOP_RIGHT);
-  printer.print((0, 32), line::BOTTOM_LEFT);
-  printer.print((48, 32), line::BOTTOM_RIGHT);
+  printer.print((0, 0), line.top_left);
+  printer.print((48, 0), line.top_right);
+  printer.print((0, 32), line.bottom_left);
+  printer.print((48, 32), line.bottom_right);
 }
 
+/// How long the search for a hint may take.
+const HINT_TIME: Duration = Duration::from_millis(500);
+/// How deep, and for how long, a puzzle move other than the solution's is searched.
+const PUZZLE_CHECK_DEPTH: u32 = 12;
+const PUZZLE_CHECK_TIME: Duration = Duration::from_millis(300);
+
+const HELP: &str = "\
+Arrow keys  move the cursor
+Enter       select a piece, then its destination
+            (select it again to cancel)
+Mouse       click a piece, then its destination,
+            or drag the piece there
+h           show a hint for the player on move
+PgUp/PgDn   step back and forward through the moves
+Tab         move to the move list, where Up/Down step
+            through the moves; click a move to see it
+Home        go to the starting position
+End, Esc    return to the game
+Enter       (after the game) rematch, new game, report
+            on the moves or save
+f           flip the board
+a           analyse the position on the board
+e           set up a position: w/b place a man, W/B a
+            king, Space clears, t changes the side to
+            move, c clears the board, r resets it,
+            Enter plays or analyses, Esc cancels
+n           start a new game
+p           solve a puzzle, or go on to the next one
+s           save the game
+l           load a game
+o           settings
+?           this help
+q           quit (an unfinished game is kept for next time)";
+
 pub fn help(s: &mut Cursive) {
-  todo!()
+  s.add_layer(Dialog::around(TextView::new(HELP)).title("Help").dismiss_button("Close"));
+}
+
+/// Runs the board's periodic work; registered for `Event::Refresh`, which only the top layer sees.
+pub fn refresh(s: &mut Cursive) {
+  if let Some(EventResult::Consumed(Some(callback))) = s.call_on_name("board", CheckersView::refresh) {
+    callback(s);
+  }
+}
+
+/// Offers to play or analyse the position being set up, once it is valid.
+pub fn setup_done(s: &mut Cursive) {
+  let valid = s.call_on_name("board", |view: &mut CheckersView| match view.setup.as_ref().map(Setup::finish) {
+    Some(Err(error)) => {
+      view.message = error.to_string();
+      false
+    }
+    other => other.is_some(),
+  });
+  if valid != Some(true) {
+    return;
+  }
+  s.add_layer(Dialog::text("Start from this position?")
+      .title("Setup")
+      .button("Play", |s| {
+        s.pop_layer();
+        s.call_on_name("board", |view: &mut CheckersView| view.finish_setup(false));
+      })
+      .button("Analyse", |s| {
+        s.pop_layer();
+        s.call_on_name("board", |view: &mut CheckersView| view.finish_setup(true));
+        show_analysis(s, true);
+      })
+      .button("Keep editing", |s| {
+        s.pop_layer();
+      }));
+}
+
+/// Shows or hides the analysis panel next to the move list, starting or stopping the search with it.
+pub fn show_analysis(s: &mut Cursive, on: bool) {
+  s.call_on_name("board", |view: &mut CheckersView| view.set_analysing(on));
+  s.call_on_name("main", |layout: &mut LinearLayout| {
+    match (layout.find_child_from_name("analysis"), on) {
+      (Some(i), false) => {
+        layout.remove_child(i);
+      }
+      (None, true) => layout.add_child(Panel::new(AnalysisView::new().with_name("analysis")).title("Analysis")),
+      _ => {}
+    }
+  });
+}
+
+/// Shows the position after `ply` moves, as picked in the move list.
+pub fn show_ply(s: &mut Cursive, ply: usize) {
+  let sync = s.call_on_name("board", |view: &mut CheckersView| {
+    view.review_ply(ply);
+    view.sync_move_list()
+  });
+  if let Some(EventResult::Consumed(Some(callback))) = sync {
+    callback(s);
+  }
+}
+
+pub fn toggle_analysis(s: &mut Cursive) {
+  let on = s.call_on_name("board", |view: &mut CheckersView| view.analysing()).unwrap_or(false);
+  show_analysis(s, !on);
+}
+
+/**
+Moves on to the next puzzle, entering puzzle mode if needed. A game under way is only left once the
+player confirms it, and is autosaved first to be resumed on the next start.
+ */
+pub fn next_puzzle(s: &mut Cursive) {
+  if s.find_name::<Dialog>("leave game").is_some() {
+    return;
+  }
+  if s.call_on_name("board", |view: &mut CheckersView| view.game_in_progress()) != Some(true) {
+    return start_puzzle(s);
+  }
+  s.add_layer(Dialog::text("Leave this game for a puzzle? It is kept to be resumed on the next start.")
+      .title("Puzzle")
+      .button("Puzzle", |s| {
+        s.pop_layer();
+        match s.call_on_name("board", |view: &mut CheckersView| view.autosave()) {
+          Some(Err(error)) => s.add_layer(Dialog::info(format!("{:#}", error)).title("Error")),
+          _ => start_puzzle(s),
+        }
+      })
+      .dismiss_button("Cancel")
+      .with_name("leave game"));
+}
+
+fn start_puzzle(s: &mut Cursive) {
+  if let Some(Err(error)) = s.call_on_name("board", CheckersView::next_puzzle) {
+    s.add_layer(Dialog::info(format!("{:#}", error)).title("Error"));
+  }
+}
+
+/// Shows each player's accuracy and the inaccuracies, mistakes and blunders of the reviewed game.
+pub fn report_dialog(s: &mut Cursive) {
+  if s.find_name::<Dialog>("report").is_some() {
+    return;
+  }
+  let text = match s.call_on_name("board", |view: &mut CheckersView| view.report_text()) {
+    Some(text) => text,
+    None => return,
+  };
+  s.add_layer(Dialog::around(TextView::new(text).scrollable())
+      .title("Game report")
+      .button("Save annotated", |s| {
+        s.pop_layer();
+        file_dialog(s, "Save annotated game", "Save", CheckersView::save_annotated);
+      })
+      .dismiss_button("Close")
+      .with_name("report"));
+}
+
+/// Offers a rematch, a new game, a review, a report or saving once a game has ended.
+pub fn post_game(s: &mut Cursive) {
+  if s.find_name::<Dialog>("post game").is_some() {
+    return;
+  }
+  let text = match s.call_on_name("board", |view: &mut CheckersView| view.post_game_text()) {
+    Some(text) => text,
+    None => return,
+  };
+  s.add_layer(Dialog::text(text)
+      .title("Game over")
+      .button("Rematch", |s| {
+        s.pop_layer();
+        s.call_on_name("board", CheckersView::rematch);
+      })
+      .button("New game", |s| {
+        s.pop_layer();
+        new_game_dialog(s);
+      })
+      .button("Review", |s| {
+        s.pop_layer();
+        s.call_on_name("board", |view: &mut CheckersView| view.review_ply(0));
+      })
+      .button("Report", |s| {
+        s.pop_layer();
+        if let Some(EventResult::Consumed(Some(callback))) = s.call_on_name("board", CheckersView::start_report) {
+          callback(s);
+        }
+      })
+      .button("Save", |s| {
+        s.pop_layer();
+        save_dialog(s);
+      })
+      .with_name("post game"));
+}
+
+/**
+Asks who plays each side, the time control, the computer's depth, the MCTS player's iterations
+and the number of hints, then starts a new game.
+ */
+pub fn new_game_dialog(s: &mut Cursive) {
+  if s.find_name::<Dialog>("new game").is_some() {
+    return;
+  }
+  let options = match s.call_on_name("board", |view: &mut CheckersView| view.options().clone()) {
+    Some(options) => options,
+    None => return,
+  };
+  let players = |group: &mut RadioGroup<PlayerKind>, current: PlayerKind| {
+    [PlayerKind::Human, PlayerKind::Computer, PlayerKind::Mcts].into_iter().fold(LinearLayout::horizontal(), |row, kind| {
+      let button = group.button(kind, format!("{}  ", kind));
+      row.child(if kind == current { button.selected() } else { button })
+    })
+  };
+  let mut white = RadioGroup::new();
+  let mut black = RadioGroup::new();
+  let mut difficulty = RadioGroup::new();
+  let levels = std::iter::once(None).chain(Difficulty::ALL.map(Some)).fold(LinearLayout::horizontal(), |row, level| {
+    let label = level.map_or(String::from("Full strength"), |level| level.to_string());
+    let button = difficulty.button(level, format!("{}  ", label));
+    row.child(if level == options.difficulty { button.selected() } else { button })
+  });
+  let form = LinearLayout::vertical()
+      .child(TextView::new("White"))
+      .child(players(&mut white, options.white))
+      .child(TextView::new("Black"))
+      .child(players(&mut black, options.black))
+      .child(TextView::new("Time control (minutes+increment, empty for none)"))
+      .child(EditView::new()
+          .content(options.time_control.map(|control| control.to_string()).unwrap_or_default())
+          .with_name("time control"))
+      .child(TextView::new("Computer difficulty"))
+      .child(levels)
+      .child(TextView::new("Computer search depth at full strength without a clock"))
+      .child(EditView::new().content(options.depth.to_string()).with_name("depth"))
+      .child(TextView::new("MCTS iterations per move"))
+      .child(EditView::new()
+          .content(options.mcts.iterations.map(|iterations| iterations.to_string()).unwrap_or_default())
+          .with_name("mcts iterations"))
+      .child(TextView::new("Hints per game (empty for no limit)"))
+      .child(EditView::new()
+          .content(options.hints.map(|hints| hints.to_string()).unwrap_or_default())
+          .with_name("hints"));
+
+  s.add_layer(Dialog::around(form)
+      .title("New game")
+      .button("Start", move |s| {
+        let time = s.call_on_name("time control", |edit: &mut EditView| edit.get_content()).unwrap();
+        let depth = s.call_on_name("depth", |edit: &mut EditView| edit.get_content()).unwrap();
+        let hints = s.call_on_name("hints", |edit: &mut EditView| edit.get_content()).unwrap();
+        let iterations = s.call_on_name("mcts iterations", |edit: &mut EditView| edit.get_content()).unwrap();
+        let time_control = match time.trim() {
+          "" => None,
+          time => match time.parse::<TimeControl>() {
+            Ok(control) => Some(control),
+            Err(error) => return s.add_layer(Dialog::info(error).title("Error")),
+          },
+        };
+        let depth = match depth.trim().parse::<u32>() {
+          Ok(depth) if depth > 0 => depth,
+          _ => return s.add_layer(Dialog::info("The depth must be a positive number").title("Error")),
+        };
+        let iterations = match iterations.trim().parse::<u64>() {
+          Ok(iterations) if iterations > 0 => iterations,
+          _ => return s.add_layer(Dialog::info("The MCTS iterations must be a positive number").title("Error")),
+        };
+        let hints = match hints.trim() {
+          "" => None,
+          hints => match hints.parse::<u32>() {
+            Ok(hints) => Some(hints),
+            Err(_) => return s.add_layer(Dialog::info("The number of hints must be a number").title("Error")),
+          },
+        };
+        s.pop_layer();
+        let (white, black, difficulty) = (*white.selection(), *black.selection(), *difficulty.selection());
+        s.call_on_name("board", move |view: &mut CheckersView| {
+          let mut options = PlayOptions { white, black, time_control, depth, difficulty, hints, ..view.options().clone() };
+          options.mcts.iterations = Some(iterations);
+          view.start_game(options);
+        });
+      })
+      .dismiss_button("Cancel")
+      .with_name("new game"));
+}
+
+/// Where an unfinished game is saved on quitting.
+pub fn autosave_path() -> Option<PathBuf> {
+  config_dir().map(|dir| dir.join("autosave.pdn"))
+}
+
+/// Asks for a file name and saves the game there.
+pub fn save_dialog(s: &mut Cursive) {
+  file_dialog(s, "Save game", "Save", |view, path| view.save(path));
 }
 
-/// Computes the terminal position corresponding to `square`.
-fn term_pos(square: i32) -> Option<Vec2> {
-  if square < 0 || square > 63 {
+/// Asks for a PDN file and replaces the game with the first game in it.
+pub fn load_dialog(s: &mut Cursive) {
+  file_dialog(s, "Load game", "Load", |view, path| view.load(path));
+}
+
+fn file_dialog(s: &mut Cursive, title: &str, label: &str, action: fn(&mut CheckersView, &Path) -> Result<()>) {
+  if s.find_name::<Dialog>("file").is_some() {
+    return;
+  }
+  let submit = move |s: &mut Cursive, path: &str| {
+    match s.call_on_name("board", |view: &mut CheckersView| action(view, Path::new(path))) {
+      Some(Err(error)) => s.add_layer(Dialog::info(format!("{:#}", error)).title("Error")),
+      _ => {
+        s.pop_layer();
+      }
+    }
+  };
+  let path = EditView::new().content("game.pdn").on_submit(submit).with_name("path").fixed_width(40);
+  s.add_layer(Dialog::around(path)
+      .title(title)
+      .button(label, move |s| {
+        let path = s.call_on_name("path", |edit: &mut EditView| edit.get_content()).unwrap();
+        submit(s, &path);
+      })
+      .dismiss_button("Cancel")
+      .with_name("file"));
+}
+
+/// Offers to continue the game that was autosaved when the program last quit.
+pub fn resume_prompt(s: &mut Cursive) {
+  s.add_layer(Dialog::text("Resume the unfinished game from last time?")
+      .title("Resume")
+      .button("Resume", |s| {
+        s.pop_layer();
+        let path = autosave_path().unwrap();
+        if let Some(Err(error)) = s.call_on_name("board", |view: &mut CheckersView| view.load(&path)) {
+          s.add_layer(Dialog::info(format!("{:#}", error)).title("Error"));
+        }
+      })
+      .button("New game", |s| {
+        s.pop_layer();
+      }));
+}
+
+/// Autosaves the game and quits.
+pub fn quit(s: &mut Cursive) {
+  // the terminal is about to be restored, so there is nowhere to report a failed autosave
+  let _ = s.call_on_name("board", |view: &mut CheckersView| view.autosave());
+  s.quit();
+}
+
+/// Shows the settings dialog, which switches the board's move hints on and off and picks its labels.
+pub fn settings(s: &mut Cursive) {
+  if s.find_name::<Dialog>("settings").is_some() {
+    return;
+  }
+  let (highlights, labels) = match s.call_on_name("board", |view: &mut CheckersView| (view.highlights(), view.labels())) {
+    Some(settings) => settings,
+    None => return,
+  };
+  let toggle = |label: &str, checked: bool, set: fn(&mut Highlights, bool)| {
+    let checkbox = Checkbox::new().with_checked(checked).on_change(move |s, checked| {
+      s.call_on_name("board", |view: &mut CheckersView| {
+        let mut highlights = view.highlights();
+        set(&mut highlights, checked);
+        view.set_highlights(highlights);
+      });
+    });
+    LinearLayout::horizontal().child(checkbox).child(TextView::new(format!(" {}", label)))
+  };
+  let list = LinearLayout::vertical()
+      .child(toggle("Show legal destinations", highlights.destinations, |h, on| h.destinations = on))
+      .child(toggle("Mark pieces that must capture", highlights.forced_captures, |h, on| h.forced_captures = on))
+      .child(toggle("Show the previous move", highlights.last_move, |h, on| h.last_move = on))
+      .child(TextView::new("\nSquare labels"));
+  let mut group = RadioGroup::new().on_change(|s, labels: &Labels| {
+    s.call_on_name("board", |view: &mut CheckersView| view.set_labels(*labels));
+  });
+  let list = [(Labels::None, "None"), (Labels::Coordinates, "Ranks and files"), (Labels::Numbers, "Square numbers")]
+      .into_iter()
+      .fold(list, |list, (value, label)| {
+        let button = group.button(value, label);
+        list.child(if value == labels { button.selected() } else { button })
+      });
+  s.add_layer(Dialog::around(list).title("Settings").dismiss_button("Close").with_name("settings"));
+}
+
+/// Computes the terminal position corresponding to `square`, with Black at the bottom if `flipped`.
+pub(crate) fn term_pos(square: i32, flipped: bool) -> Option<Vec2> {
+  if !(0..=63).contains(&square) {
     None
   } else {
+    let square = if flipped { 63 - square } else { square };
     let row = 7 - (square >> 3);
     let col = square & 7;
     Some(Vec2::from((3 + 6 * col, 2 + 4 * row)))
   }
 }
+
+/// Finds the square whose cell contains the terminal position `pos`; the inverse of `term_pos`.
+pub(crate) fn square_at(pos: Vec2, flipped: bool) -> Option<i32> {
+  if pos.x.is_multiple_of(6) || pos.y.is_multiple_of(4) || pos.x >= 48 || pos.y >= 32 {
+    return None;
+  }
+  let col = (pos.x / 6) as i32;
+  let row = (pos.y / 4) as i32;
+  let square = (7 - row) * 8 + col;
+  Some(if flipped { 63 - square } else { square })
+}
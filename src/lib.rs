@@ -1,3 +1,4 @@
+pub mod engine;
 pub mod game;
 pub mod interface;
 
@@ -1,7 +1,518 @@
-use eyre::Result;
-use checkers::interface::run;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use eyre::{eyre, Result};
+use checkers::engine::difficulty::Difficulty;
+use checkers::engine::mcts::{MctsLimits, Playout};
+use checkers::engine::network::Network;
+use checkers::engine::search::{SearchLimits, Searcher};
+use checkers::engine::training::{DataFormat, SelfPlayOptions, TrainingOptions};
+use checkers::game::{Checkerboard, PlayerColor};
+use checkers::game::notation::parse_fen;
+use checkers::interface::{CheckersGame, PlayerKind, PlayOptions, run};
+use checkers::interface::cli::run_cli;
+use checkers::interface::clock::TimeControl;
+use checkers::interface::commands::{analyze, convert, ConvertFormat, extract_game_puzzles, generate_self_play,
+                                    load_position, MatchOptions, OpeningChoice, perft_report, review_games, run_match,
+                                    SelfPlayDataOptions, train_network, TrainOptions};
+use checkers::interface::protocol::run_engine;
+use checkers::interface::theme::Theme;
+
+/// Play and analyse checkers (English draughts).
+#[derive(Parser)]
+#[command(name = "checkers", version, args_conflicts_with_subcommands = true)]
+struct Cli {
+  #[command(subcommand)]
+  command: Option<Command>,
+  #[command(flatten)]
+  play: PlayArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Play in the terminal UI (the default)
+  Play(PlayArgs),
+  /// Play a line-based game on stdin and stdout
+  Cli {
+    /// Start from this position instead of the standard one
+    #[arg(long)]
+    fen: Option<String>,
+  },
+  /// Run the line-based engine protocol on stdin and stdout
+  Engine {
+    /// Number of search threads
+    #[arg(long, default_value_t = 1)]
+    threads: usize,
+    /// Evaluate positions with the network in this weights file
+    #[arg(long)]
+    network: Option<PathBuf>,
+  },
+  /// Count the leaf nodes of the move tree to verify move generation
+  Perft {
+    /// Number of plies to search
+    depth: u32,
+    /// Start from this position instead of the standard one
+    #[arg(long)]
+    fen: Option<String>,
+    /// Also print the count below each legal move
+    #[arg(long)]
+    divide: bool,
+  },
+  /// Search a position given as a FEN string or a PDN file (its final position)
+  Analyze {
+    /// FEN string or path to a PDN file
+    input: String,
+    #[command(flatten)]
+    search: SearchArgs,
+  },
+  /// Convert a PDN file or FEN string to another format
+  Convert {
+    /// FEN string or path to a PDN file
+    input: String,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Fen)]
+    to: Format,
+  },
+  /// Play the engine against itself with different settings
+  Match(MatchArgs),
+  /// Mark the inaccuracies, mistakes and blunders in the games of a PDN file
+  Review {
+    /// PDN file with the games to review
+    input: PathBuf,
+    #[command(flatten)]
+    search: SearchArgs,
+    /// Write the annotated games to this file and print a summary instead
+    #[arg(long)]
+    output: Option<PathBuf>,
+  },
+  /// Solve puzzles and work with puzzle files
+  #[command(subcommand)]
+  Puzzles(PuzzlesCommand),
+  /// Train an evaluation network on self-play games
+  Train(TrainArgs),
+  /// Play the engine against itself and write the positions, scored and with the results, to a file
+  Selfplay(SelfplayArgs),
+}
+
+#[derive(Subcommand)]
+enum PuzzlesCommand {
+  /// Solve puzzles in the terminal UI, picked to match your puzzle rating
+  Train {
+    /// Puzzle file to use instead of the bundled puzzles
+    #[arg(long)]
+    file: Option<PathBuf>,
+  },
+  /// Find puzzles in a collection of games: positions where a shot wins, whether played or not
+  Extract {
+    /// PDN file with the games to search
+    input: PathBuf,
+    #[command(flatten)]
+    search: SearchArgs,
+    /// Write the puzzles to this file and list where they were found instead
+    #[arg(long)]
+    output: Option<PathBuf>,
+  },
+}
+
+#[derive(Args)]
+struct PlayArgs {
+  /// Who plays White
+  #[arg(long, value_enum, default_value_t = Side::Human)]
+  white: Side,
+  /// Who plays Black
+  #[arg(long, value_enum, default_value_t = Side::Human)]
+  black: Side,
+  /// Rules to play by
+  #[arg(long, value_enum, default_value_t = Variant::English)]
+  variant: Variant,
+  /// Start from this position instead of the standard one
+  #[arg(long)]
+  fen: Option<String>,
+  /// Time control as minutes[+increment seconds], e.g. 5+3
+  #[arg(long)]
+  time: Option<TimeControl>,
+  /// Search depth of the computer player without a clock; with --time it plans its own time
+  #[arg(long, default_value_t = 8)]
+  depth: u32,
+  /// Let the computer player play at this level, with its own depth, instead of at full strength
+  #[arg(long, value_enum)]
+  difficulty: Option<Level>,
+  /// Number of threads for the computer's searches and the analysis panel
+  #[arg(long, default_value_t = 1)]
+  threads: usize,
+  /// Let the computer think on your time about the position after the reply it expects
+  #[arg(long)]
+  ponder: bool,
+  /// Iterations of the MCTS player per move
+  #[arg(long, default_value_t = 20_000)]
+  mcts_iterations: u64,
+  /// Time limit of the MCTS player per move in milliseconds
+  #[arg(long)]
+  mcts_time: Option<u64>,
+  /// How the MCTS player plays positions out
+  #[arg(long, value_enum, default_value_t = PlayoutKind::Heuristic)]
+  playout: PlayoutKind,
+  /// Evaluate positions with the network in this weights file
+  #[arg(long)]
+  network: Option<PathBuf>,
+  /// Board theme: default, ascii, colorblind or the path of a theme file [default: theme.toml in
+  /// the configuration directory if present]
+  #[arg(long)]
+  theme: Option<String>,
+  /// Open the analysis panel, which searches the position on the board in the background
+  #[arg(long)]
+  analyse: bool,
+  /// Number of candidate moves the analysis panel shows
+  #[arg(long, default_value_t = 3)]
+  lines: usize,
+  /// Number of hints allowed per game [default: no limit]
+  #[arg(long)]
+  hints: Option<u32>,
+}
+
+#[derive(Args)]
+struct SearchArgs {
+  /// Search depth in plies
+  #[arg(long)]
+  depth: Option<u32>,
+  /// Search time in milliseconds
+  #[arg(long)]
+  movetime: Option<u64>,
+  /// Number of search threads; one gives the same result on every run
+  #[arg(long, default_value_t = 1)]
+  threads: usize,
+  /// Evaluate positions with the network in this weights file
+  #[arg(long)]
+  network: Option<PathBuf>,
+}
+
+impl SearchArgs {
+  fn limits(&self, default_depth: u32) -> SearchLimits {
+    let time = self.movetime.map(Duration::from_millis);
+    let depth = match (self.depth, time) {
+      (None, None) => Some(default_depth),
+      (depth, _) => depth,
+    };
+    SearchLimits { depth, time, ..SearchLimits::default() }
+  }
+
+  fn searcher(&self, default_depth: u32) -> Result<Searcher> {
+    let network = load_network(self.network.as_deref())?;
+    Ok(Searcher::new(self.limits(default_depth)).with_threads(self.threads).with_network(network))
+  }
+}
+
+#[derive(Args)]
+struct TrainArgs {
+  /// Write the trained weights to this file
+  output: PathBuf,
+  /// Number of self-play games to play and train on [default: 100, or 0 with --data]
+  #[arg(long)]
+  games: Option<u32>,
+  /// Also train on the positions of these binary files written by selfplay
+  #[arg(long, num_args = 1..)]
+  data: Vec<PathBuf>,
+  /// Search depth of the self-play games, which also score their positions
+  #[arg(long, default_value_t = 4)]
+  depth: u32,
+  /// Number of random moves opening each game
+  #[arg(long, default_value_t = 6)]
+  random_plies: u32,
+  /// Number of passes over the positions
+  #[arg(long, default_value_t = 10)]
+  epochs: u32,
+  #[arg(long, default_value_t = 0.005)]
+  learning_rate: f32,
+  /// How much the game results count against the search scores, from 0 to 1
+  #[arg(long, default_value_t = 0.3)]
+  outcome_weight: f32,
+  /// Continue training these weights, which also play the self-play games
+  #[arg(long)]
+  init: Option<PathBuf>,
+  /// Seed for the random moves and weights, to repeat a run
+  #[arg(long)]
+  seed: Option<u64>,
+}
+
+#[derive(Args)]
+struct SelfplayArgs {
+  /// Write the positions to this file
+  output: PathBuf,
+  /// Number of games
+  #[arg(long, default_value_t = 100)]
+  games: u32,
+  /// Number of games played at once
+  #[arg(long, default_value_t = 1)]
+  threads: usize,
+  /// How the games start
+  #[arg(long, value_enum, default_value_t = Openings::Random)]
+  openings: Openings,
+  /// Deal the openings in this PDN file instead, such as the three-move ballot
+  #[arg(long, conflicts_with = "openings")]
+  openings_file: Option<PathBuf>,
+  /// Number of random moves opening each game with random openings
+  #[arg(long, default_value_t = 6)]
+  random_plies: u32,
+  /// Search depth, which also scores the positions
+  #[arg(long, default_value_t = 4)]
+  depth: u32,
+  /// Spread of the move choice in hundredths of a man; 0 always plays the best move
+  #[arg(long, default_value_t = 0.0)]
+  temperature: f32,
+  /// Only randomize moves by temperature before this ply
+  #[arg(long, default_value_t = 20)]
+  temperature_plies: u32,
+  /// Adjudicate games as draws after this many plies
+  #[arg(long, default_value_t = 200)]
+  max_plies: u32,
+  #[arg(long, value_enum, default_value_t = DataFileFormat::Binary)]
+  format: DataFileFormat,
+  /// Evaluate positions with the network in this weights file
+  #[arg(long)]
+  network: Option<PathBuf>,
+  /// Seed for the random moves, to repeat a run
+  #[arg(long)]
+  seed: Option<u64>,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Openings {
+  /// Random moves from the starting position
+  Random,
+  /// Every legal three-move opening in random order, lopsided ones included
+  ThreeMove,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum DataFileFormat {
+  /// 20 bytes per position, readable by train --data
+  Binary,
+  /// A JSON object per line
+  Jsonl,
+}
+
+#[derive(Args)]
+struct MatchArgs {
+  /// Number of games; each opening is played twice with colors swapped
+  #[arg(long, default_value_t = 2)]
+  games: u32,
+  /// Search depth of the first engine
+  #[arg(long, default_value_t = 6)]
+  depth: u32,
+  /// Search depth of the second engine
+  #[arg(long, default_value_t = 4)]
+  opponent_depth: u32,
+  /// Let the second engine use Monte Carlo tree search with this many iterations per move
+  #[arg(long)]
+  opponent_mcts: Option<u64>,
+  /// Let the second engine play at this difficulty level instead
+  #[arg(long, value_enum, conflicts_with = "opponent_mcts")]
+  opponent_difficulty: Option<Level>,
+  /// Adjudicate games as draws after this many plies
+  #[arg(long, default_value_t = 200)]
+  max_plies: u32,
+  /// Write the games to this PDN file
+  #[arg(long)]
+  pdn: Option<PathBuf>,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Side {
+  Human,
+  /// Alpha-beta search
+  Computer,
+  /// Monte Carlo tree search
+  Mcts,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Level {
+  Beginner,
+  Novice,
+  Intermediate,
+  Advanced,
+}
+
+impl From<Level> for Difficulty {
+  fn from(level: Level) -> Difficulty {
+    match level {
+      Level::Beginner => Difficulty::Beginner,
+      Level::Novice => Difficulty::Novice,
+      Level::Intermediate => Difficulty::Intermediate,
+      Level::Advanced => Difficulty::Advanced,
+    }
+  }
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum PlayoutKind {
+  /// Uniformly random moves
+  Random,
+  /// Random moves, preferring promotions and moves that leave nothing to capture
+  Heuristic,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Variant {
+  /// English draughts / American checkers on an 8x8 board
+  English,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Format {
+  Fen,
+  Pdn,
+  Board,
+}
 
 fn main() -> Result<()> {
-  run();
+  let cli = Cli::parse();
+  match cli.command.unwrap_or(Command::Play(cli.play)) {
+    Command::Play(args) => run(play_options(args)?),
+    Command::Cli { fen } => {
+      let game = match fen {
+        Some(fen) => {
+          let (board, color) = parse_position(&fen)?;
+          CheckersGame::from_position(board, color)
+        }
+        None => CheckersGame::new(),
+      };
+      run_cli(game)?
+    }
+    Command::Engine { threads, network } => {
+      let searcher = Searcher::new(SearchLimits::default()).with_threads(threads);
+      run_engine(searcher.with_network(load_network(network.as_deref())?))?
+    }
+    Command::Perft { depth, fen, divide } => {
+      let (board, color) = match fen {
+        Some(fen) => parse_position(&fen)?,
+        None => (Checkerboard::new(), PlayerColor::White),
+      };
+      perft_report(&board, color, depth, divide, &mut std::io::stdout())?
+    }
+    Command::Analyze { input, search } => {
+      let (board, color) = load_position(&input)?;
+      analyze(&board, color, &mut search.searcher(10)?, &mut std::io::stdout())?
+    }
+    Command::Convert { input, to } => {
+      let format = match to {
+        Format::Fen => ConvertFormat::Fen,
+        Format::Pdn => ConvertFormat::Pdn,
+        Format::Board => ConvertFormat::Board,
+      };
+      convert(&input, format, &mut std::io::stdout())?
+    }
+    Command::Match(args) => {
+      let options = MatchOptions {
+        games: args.games,
+        first: SearchLimits::depth(args.depth),
+        second: SearchLimits::depth(args.opponent_depth),
+        second_mcts: args.opponent_mcts.map(|iterations| {
+          (MctsLimits { iterations: Some(iterations), time: None }, Playout::Heuristic)
+        }),
+        second_difficulty: args.opponent_difficulty.map(Difficulty::from),
+        max_plies: args.max_plies,
+        pdn: args.pdn,
+      };
+      run_match(&options, &mut std::io::stdout())?;
+    }
+    Command::Puzzles(PuzzlesCommand::Train { file }) => {
+      run(PlayOptions { puzzle_file: file, puzzles: true, theme: Theme::load(None)?, ..PlayOptions::default() })
+    }
+    Command::Puzzles(PuzzlesCommand::Extract { input, search, output }) => {
+      extract_game_puzzles(&input, &mut search.searcher(8)?, output.as_deref(), &mut std::io::stdout())?
+    }
+    Command::Review { input, search, output } => {
+      review_games(&input, &mut search.searcher(6)?, output.as_deref(), &mut std::io::stdout())?
+    }
+    Command::Train(args) => {
+      if !(0.0..=1.0).contains(&args.outcome_weight) {
+        return Err(eyre!("the outcome weight must be between 0 and 1"));
+      }
+      let options = TrainOptions {
+        games: args.games.unwrap_or(if args.data.is_empty() { 100 } else { 0 }),
+        self_play: SelfPlayOptions { depth: args.depth, random_plies: args.random_plies, ..SelfPlayOptions::default() },
+        training: TrainingOptions {
+          epochs: args.epochs,
+          learning_rate: args.learning_rate,
+          outcome_weight: args.outcome_weight,
+        },
+        init: args.init,
+        data: args.data,
+        output: args.output,
+        seed: args.seed,
+      };
+      train_network(&options, &mut std::io::stdout())?
+    }
+    Command::Selfplay(args) => {
+      let openings = match (args.openings_file, args.openings) {
+        (Some(path), _) => OpeningChoice::File(path),
+        (None, Openings::Random) => OpeningChoice::Random,
+        (None, Openings::ThreeMove) => OpeningChoice::ThreeMove,
+      };
+      let random_plies = if let OpeningChoice::Random = openings { args.random_plies } else { 0 };
+      let options = SelfPlayDataOptions {
+        games: args.games,
+        threads: args.threads,
+        openings,
+        game: SelfPlayOptions {
+          depth: args.depth,
+          random_plies,
+          max_plies: args.max_plies,
+          temperature: args.temperature,
+          temperature_plies: args.temperature_plies,
+          network: load_network(args.network.as_deref())?,
+        },
+        format: match args.format {
+          DataFileFormat::Binary => DataFormat::Binary,
+          DataFileFormat::Jsonl => DataFormat::Jsonl,
+        },
+        path: args.output,
+        seed: args.seed,
+      };
+      generate_self_play(&options, &mut std::io::stdout())?;
+    }
+  }
   Ok(())
 }
+
+fn play_options(args: PlayArgs) -> Result<PlayOptions> {
+  let kind = |side| match side {
+    Side::Human => PlayerKind::Human,
+    Side::Computer => PlayerKind::Computer,
+    Side::Mcts => PlayerKind::Mcts,
+  };
+  let playout = match args.playout {
+    PlayoutKind::Random => Playout::Random,
+    PlayoutKind::Heuristic => Playout::Heuristic,
+  };
+  let Variant::English = args.variant;
+  Ok(PlayOptions {
+    white: kind(args.white),
+    black: kind(args.black),
+    position: args.fen.as_deref().map(parse_position).transpose()?,
+    time_control: args.time,
+    depth: args.depth,
+    difficulty: args.difficulty.map(Difficulty::from),
+    threads: args.threads,
+    ponder: args.ponder,
+    mcts: MctsLimits { iterations: Some(args.mcts_iterations), time: args.mcts_time.map(Duration::from_millis) },
+    playout,
+    network: load_network(args.network.as_deref())?,
+    theme: Theme::load(args.theme.as_deref())?,
+    analysis_lines: args.lines,
+    analysis: args.analyse,
+    hints: args.hints,
+    ..PlayOptions::default()
+  })
+}
+
+fn load_network(path: Option<&Path>) -> Result<Option<Arc<Network>>> {
+  Ok(path.map(Network::load).transpose()?.map(Arc::new))
+}
+
+fn parse_position(fen: &str) -> Result<(Checkerboard, PlayerColor)> {
+  Ok(parse_fen(fen)?)
+}
@@ -1,9 +1,43 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cursive::{Cursive, Vec2, View};
+use cursive::event::{Event, EventResult, Key, MouseButton, MouseEvent};
+
 use indoc::indoc;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 
 use crate::game::bitboard::Bitboard;
-use crate::game::{Checkerboard, MoveResult};
-use crate::game::moves::piece_moves;
+use crate::game::{Checkerboard, MoveError, MoveResult, PositionError};
+use crate::engine::difficulty::Difficulty;
+use crate::engine::mcts::{Mcts, MctsLimits, Playout};
+use crate::engine::network::{Accumulator, Network};
+use crate::engine::review::{review_game, MoveQuality};
+use crate::engine::search::{MATE, SearchInfo, SearchLimits, Searcher};
+use crate::engine::time::{ClockTime, MOVE_OVERHEAD, TimeManager};
+use crate::engine::training::{choose_by_temperature, DataFormat, play_training_game, read_samples, Sample, SelfPlayOptions, train,
+                              TrainingOptions};
+use crate::engine::tt::{Bound, position_key, TableEntry, TranspositionTable};
+use crate::game::moves::{legal_moves, perft, piece_destinations, resolve_move, Move};
+use crate::game::notation::{number_square, parse_fen, square_number, to_fen, START_FEN};
+use crate::game::pdn::parse_pdn;
 use crate::game::PlayerColor::{Black, White};
+use crate::interface::{cli, protocol};
+use crate::interface::commands::{opening_lines, openings};
+use crate::interface::movelist::MoveListView;
+use crate::interface::{CheckersGame, GameResult};
+use crate::interface::puzzles::{extract_puzzles, load_puzzles, parse_puzzles, write_puzzles, PuzzleAttempt, PuzzleStats, PuzzleStep,
+                                OTHER_GAIN, SHOT_GAIN};
+use crate::interface::setup::Setup;
+use crate::interface::theme::Theme;
+use crate::interface::ui::{square_at, term_pos};
+
+fn squash(s: &str) -> String {
+  s.chars().filter(|c| !c.is_whitespace()).collect()
+}
 
 #[test]
 fn bitboard_display() {
@@ -23,7 +57,7 @@ fn bitboard_display() {
 #[test]
 fn start_board_display() {
   let start = Checkerboard::new();
-  assert_eq!(start.to_string().retain(|c| !c.is_whitespace()), String::from("
+  assert_eq!(squash(&start.to_string()), squash("
        ● ● ● ●
       ● ● ● ●
        ● ● ● ●
@@ -32,7 +66,7 @@ fn start_board_display() {
       ○ ○ ○ ○
        ○ ○ ○ ○
       ○ ○ ○ ○
-  ").retain(|c| !c.is_whitespace()));
+  "));
 }
 
 #[test]
@@ -48,7 +82,7 @@ fn make_some_moves() {
   board.make_move(White, Bitboard::from(1 << 20), Bitboard::from(1 << 27));
   board.make_move(Black, Bitboard::from(1 << 41), Bitboard::from(1 << 34));
   board.make_move(White, Bitboard::from(1 << 27), Bitboard::from(1 << 41));
-  assert_eq!(board.to_string().retain(|c| !c.is_whitespace()), String::from("
+  assert_eq!(squash(&board.to_string()), squash("
        ● ● ● ●
       ● ● ● ●
        ○ ● ● ●
@@ -57,41 +91,27 @@ fn make_some_moves() {
       ○ ○   ○
        ○ ○ ○ ○
       ○ ○ ○ ○
-  ").retain(|c| !c.is_whitespace()));
+  "));
 }
 
 #[test]
 fn promotion() {
-  let mut board = Checkerboard::new();
-  board.make_move(White, Bitboard::from(1 << 20), Bitboard::from(1 << 27));
-  board.make_move(Black, Bitboard::from(1 << 41), Bitboard::from(1 << 34));
-  board.make_move(White, Bitboard::from(1 << 27), Bitboard::from(1 << 41));
-  board.make_move(Black, Bitboard::from(1 << 48), Bitboard::from(1 << 34));
-  board.make_move(White, Bitboard::from(1 << 11), Bitboard::from(1 << 20));
-  board.make_move(Black, Bitboard::from(1 << 34), Bitboard::from(1 << 27));
-  board.make_move(White, Bitboard::from(1 << 20), Bitboard::from(1 << 34));
-  board.make_move(Black, Bitboard::from(1 << 43), Bitboard::from(1 << 25));
-  board.make_move(White, Bitboard::from(1 << 18), Bitboard::from(1 << 32));
-  board.make_move(Black, Bitboard::from(1 << 52), Bitboard::from(1 << 43));
-  board.make_move(White, Bitboard::from(1 << 22), Bitboard::from(1 << 29));
-  board.make_move(Black, Bitboard::from(1 << 59), Bitboard::from(1 << 52));
-  board.make_move(White, Bitboard::from(1 << 16), Bitboard::from(1 << 25));
-  board.make_move(Black, Bitboard::from(1 << 57), Bitboard::from(1 << 48));
-  board.make_move(White, Bitboard::from(1 << 15), Bitboard::from(1 << 22));
-  board.make_move(Black, Bitboard::from(1 << 50), Bitboard::from(1 << 41));
-  board.make_move(White, Bitboard::from(1 << 32), Bitboard::from(1 << 50));
-  board.make_move(Black, Bitboard::from(1 << 48), Bitboard::from(1 << 41));
-  board.make_move(White, Bitboard::from(1 << 50), Bitboard::from(1 << 57));
-  assert_eq!(board.to_string().retain(|c| !c.is_whitespace()), String::from("
-       ☆   ● ●
-          ● ●
-       ● ● ● ●
+  // crowning ends the move, even though the new king on 3 could go on to jump 8
+  let (mut board, _) = parse_fen("W:W10,30:B7,8").unwrap();
+  assert_eq!(board.make_move(White, sq(10), sq(3)), MoveResult::Valid(Black));
+  assert_eq!(board.pending_capture(), None);
+  assert_eq!(board.make_move(Black, sq(8), sq(12)), MoveResult::Valid(White));
+  assert_eq!(board.make_move(White, sq(3), sq(7)), MoveResult::Valid(Black));
+  assert_eq!(squash(&board.to_string()), squash("
 
-       ○   ○
-            ○
-       ○   ○
-      ○ ○ ○ ○
-  ").retain(|c| !c.is_whitespace()));
+           ☆
+              ●
+
+
+
+
+        ○
+  "));
 }
 
 #[test]
@@ -100,3 +120,702 @@ fn midsquare() {
   let end = Bitboard::from(1 << 41);
   assert_eq!(Bitboard::midsquare(start, end), Bitboard::from(1 << 34));
 }
+
+#[test]
+fn edge_masks() {
+  // the second row is a whole row's shift away; shifting by 9 also took in the end square of the
+  // third row, so a piece there could never jump towards the near edge
+  assert_eq!(crate::game::bitboard::BOTTOM_TWO, Bitboard::from(0xffff));
+  assert_eq!(crate::game::bitboard::TOP_TWO, Bitboard::from(0xffff << 48));
+}
+
+#[test]
+fn square_numbers() {
+  assert_eq!(square_number(Bitboard::from(1)), Some(29));
+  assert_eq!(square_number(Bitboard::from(1 << 63)), Some(4));
+  assert_eq!(square_number(Bitboard::from(1 << 57)), Some(1));
+  assert_eq!(square_number(Bitboard::from(1 << 1)), None);
+  for n in 1..=32 {
+    assert_eq!(number_square(n).and_then(square_number), Some(n));
+  }
+}
+
+#[test]
+fn move_notation() {
+  let mv: Move = "22x15x8".parse().unwrap();
+  assert_eq!(mv.to_string(), "22x15x8");
+  assert!(mv.is_capture());
+  assert_eq!("11-15".parse::<Move>().unwrap().to_string(), "11-15");
+  assert!("11-15-18".parse::<Move>().is_err());
+  assert!("0-4".parse::<Move>().is_err());
+}
+
+#[test]
+fn opening_moves() {
+  let moves: Vec<String> = legal_moves(&Checkerboard::new(), White).iter().map(Move::to_string).collect();
+  assert_eq!(moves, ["21-17", "22-17", "22-18", "23-18", "23-19", "24-19", "24-20"]);
+}
+
+#[test]
+fn cli_script() {
+  let input = "22-18\n11-15\n18x11\n8x15\n9-13\nquit\n";
+  let mut output = Vec::new();
+  cli::play(CheckersGame::new(), input.as_bytes(), &mut output).unwrap();
+  let output = String::from_utf8(output).unwrap();
+  assert!(output.contains("Invalid move 9-13: There is no piece of yours on 9"));
+  assert!(output.ends_with("White to move\n"));
+}
+
+#[test]
+fn perft_start() {
+  let counts: Vec<u64> = (1..=7).map(|depth| perft(&Checkerboard::new(), White, depth)).collect();
+  assert_eq!(counts, [7, 49, 302, 1469, 7361, 36768, 179740]);
+}
+
+#[test]
+fn perft_kings() {
+  let (board, color) = parse_fen("W:WK10,K22,18,27,29,5:B14,15,K19,23,6,K31,K2").unwrap();
+  assert_eq!(perft(&board, color, 6), 5550);
+}
+
+#[test]
+fn fen_round_trip() {
+  let (board, color) = parse_fen("W:W21-32:B1-12").unwrap();
+  assert_eq!((board, color), (Checkerboard::new(), White));
+  assert_eq!(to_fen(&board, color), START_FEN);
+  let fen = "B:W18,K30:B6,K11";
+  let (board, color) = parse_fen(fen).unwrap();
+  assert_eq!(color, Black);
+  assert_eq!(to_fen(&board, color), fen);
+  assert!(parse_fen("W:W18:B18").is_err());
+}
+
+#[test]
+fn pdn_round_trip() {
+  let text = indoc! { r#"
+      [Event "Test"]
+      [Result "1-0"]
+
+      1. 11-15 {center} 22-18 2. 15x22 25x18 3. 8-11! 29-25 $1 4. 4-8 1-0
+  "#};
+  let games = parse_pdn(text).unwrap();
+  assert_eq!(games.len(), 1);
+  let game = &games[0];
+  assert_eq!(game.tag("Event"), Some("Test"));
+  assert_eq!(game.moves.len(), 7);
+  assert_eq!(game.result, "1-0");
+  let reparsed = parse_pdn(&game.to_string()).unwrap();
+  assert_eq!(&reparsed[0], game);
+  let replayed = CheckersGame::from_pdn(game).unwrap();
+  assert_eq!(replayed.on_move(), White);
+
+  // published games have no FEN tag and start with Black, while games played here start with White
+  let game = &parse_pdn("1. 11-15 23-19 2. 8-11 22-17 *").unwrap()[0];
+  let (board, color) = game.final_position().unwrap();
+  assert_eq!(to_fen(&board, color), "B:W17,19,21,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,9,10,11,12,15");
+  let mut new_game = CheckersGame::new();
+  new_game.play_move(&"22-18".parse().unwrap());
+  let pdn = new_game.to_pdn();
+  assert_eq!(pdn.tag("FEN"), Some(START_FEN));
+  assert!(pdn.to_string().contains("1. 22-18 *"));
+  let reread = CheckersGame::from_pdn(&parse_pdn(&pdn.to_string()).unwrap()[0]).unwrap();
+  assert_eq!((reread.board(), reread.on_move()), (new_game.board(), Black));
+}
+
+#[test]
+fn search_finds_forced_win() {
+  // after 30-26 both black men walk into captures, whatever Black plays
+  let (board, color) = parse_fen("W:W27,30:B18,20").unwrap();
+  let info = Searcher::new(SearchLimits::depth(8)).search(&board, color);
+  assert_eq!(info.score, MATE - 5);
+  assert_eq!(info.best_move().map(Move::to_string).as_deref(), Some("30-26"));
+}
+
+#[test]
+fn parallel_search() {
+  // the same position reached in a different order has the same key
+  let mut one = Checkerboard::new();
+  let mut other = Checkerboard::new();
+  for (board, moves) in [(&mut one, ["22-18", "12-16", "21-17"]), (&mut other, ["21-17", "12-16", "22-18"])] {
+    for (i, mv) in moves.iter().enumerate() {
+      board.apply(if i % 2 == 0 { White } else { Black }, &mv.parse().unwrap());
+    }
+  }
+  assert_eq!(position_key(&one, Black), position_key(&other, Black));
+  assert_ne!(position_key(&one, Black), position_key(&one, White));
+
+  let table = TranspositionTable::new(16);
+  let entry = TableEntry { score: -MATE + 7, depth: 9, bound: Bound::Upper, best: Some(3) };
+  table.store(42, entry);
+  assert_eq!(table.probe(42), Some(entry));
+  assert_eq!(table.probe(42 + 16), None);
+
+  // one thread always gives the same result
+  let board = Checkerboard::new();
+  let first = Searcher::new(SearchLimits::depth(7)).search(&board, White);
+  let second = Searcher::new(SearchLimits::depth(7)).search(&board, White);
+  assert_eq!((first.score, &first.pv, first.nodes), (second.score, &second.pv, second.nodes));
+
+  let (board, color) = parse_fen("W:W27,30:B18,20").unwrap();
+  let info = Searcher::new(SearchLimits::depth(8)).with_threads(3).search(&board, color);
+  assert_eq!(info.score, MATE - 5);
+  assert_eq!(info.best_move().map(Move::to_string).as_deref(), Some("30-26"));
+  let lines = Searcher::new(SearchLimits::depth(6)).with_threads(3).search_lines(&Checkerboard::new(), White, 2, |_| {});
+  assert_eq!(lines.len(), 2);
+}
+
+#[test]
+fn engine_protocol() {
+  let input = "isready\nposition startpos moves 22-18 11-15\ngo depth 3\nposition fen bogus\nquit\n";
+  let mut output = Vec::new();
+  protocol::serve(input.as_bytes(), &mut output, Searcher::new(SearchLimits::default())).unwrap();
+  let output = String::from_utf8(output).unwrap();
+  assert!(output.starts_with("readyok\n"));
+  assert!(output.contains("info depth 3"));
+  assert!(output.contains("bestmove 18x11"));
+  assert!(output.contains("error"));
+}
+
+fn sq(number: u8) -> Bitboard {
+  number_square(number).unwrap()
+}
+
+#[test]
+fn move_errors() {
+  let (mut board, _) = parse_fen("W:W22,27:B18").unwrap();
+  let mut attempt = |start, end| board.make_move(White, sq(start), sq(end));
+  assert_eq!(attempt(26, 23), MoveResult::Invalid(MoveError::NotYourPiece(sq(26))));
+  assert_eq!(attempt(22, 18), MoveResult::Invalid(MoveError::DestinationOccupied(sq(18))));
+  assert_eq!(attempt(27, 19), MoveResult::Invalid(MoveError::NotDiagonal));
+  assert_eq!(attempt(27, 31), MoveResult::Invalid(MoveError::MenCannotMoveBackwards));
+  assert_eq!(attempt(27, 18), MoveResult::Invalid(MoveError::DestinationOccupied(sq(18))));
+  assert_eq!(attempt(27, 20), MoveResult::Invalid(MoveError::NothingToCapture));
+  assert_eq!(attempt(27, 23), MoveResult::Invalid(MoveError::CaptureMandatory(sq(22))));
+  assert_eq!(attempt(22, 15), MoveResult::Valid(Black));
+  let (board, _) = parse_fen("W:W22,27:B18,11").unwrap();
+  assert_eq!(resolve_move(&board, White, &"22x15".parse().unwrap()), Err(MoveError::MustContinueCapture(sq(15))));
+  assert_eq!(MoveError::CaptureMandatory(sq(9) | sq(14) | sq(23)).to_string(),
+             "Capture is mandatory: 9, 14 and 23 can capture");
+}
+
+#[test]
+fn capture_sequence_errors() {
+  let (board, color) = parse_fen("W:W22,27:B18,11").unwrap();
+  let mut game = CheckersGame::from_position(board, color);
+  assert_eq!(game.play_step(sq(27), sq(24)), MoveResult::Invalid(MoveError::CaptureMandatory(sq(22))));
+  assert_eq!(game.play_step(sq(22), sq(15)), MoveResult::Valid(White));
+  assert_eq!(game.play_step(sq(27), sq(23)), MoveResult::Invalid(MoveError::MustContinueCapture(sq(15))));
+  assert_eq!(game.play_move(&"15x8".parse().unwrap()), MoveResult::Invalid(MoveError::MustContinueCapture(sq(15))));
+  assert_eq!(game.play_step(sq(15), sq(8)), MoveResult::Valid(Black));
+  assert_eq!(game.history().last().map(Move::to_string).as_deref(), Some("22x15x8"));
+  assert_eq!(game.play_move(&"22x15".parse().unwrap()), MoveResult::Invalid(MoveError::GameOver));
+}
+
+#[test]
+fn pending_capture() {
+  let (mut board, _) = parse_fen("W:W22,27:B18,11").unwrap();
+  assert_eq!(board.make_move(White, sq(22), sq(15)), MoveResult::Valid(White));
+  assert_eq!(board.pending_capture(), Some((White, sq(15))));
+  assert_eq!(board.make_move(Black, sq(11), sq(16)), MoveResult::Invalid(MoveError::NotYourTurn(White)));
+  assert_eq!(board.make_move(White, sq(27), sq(23)), MoveResult::Invalid(MoveError::MustContinueCapture(sq(15))));
+  assert!(legal_moves(&board, Black).is_empty());
+  let moves: Vec<String> = legal_moves(&board, White).iter().map(Move::to_string).collect();
+  assert_eq!(moves, ["15x8"]);
+  assert_eq!(board.make_move(White, sq(15), sq(8)), MoveResult::Valid(Black));
+  assert_eq!(board.pending_capture(), None);
+}
+
+#[test]
+fn piece_destinations_respect_captures() {
+  let (mut board, _) = parse_fen("W:W22,27,K32:B18,11").unwrap();
+  assert_eq!(piece_destinations(&board, White, sq(22)), sq(15));
+  assert_eq!(piece_destinations(&board, White, sq(27)), Bitboard::new());
+  assert_eq!(piece_destinations(&board, Black, sq(11)), Bitboard::new());
+  let (quiet, _) = parse_fen("W:W27,K32:B11").unwrap();
+  assert_eq!(piece_destinations(&quiet, White, sq(27)), sq(23) | sq(24));
+  assert_eq!(piece_destinations(&quiet, White, sq(32)), sq(28));
+  board.make_move(White, sq(22), sq(15));
+  assert_eq!(piece_destinations(&board, White, sq(15)), sq(8));
+  assert_eq!(piece_destinations(&board, White, sq(32)), Bitboard::new());
+}
+
+#[test]
+fn mouse_squares() {
+  for flipped in [false, true] {
+    for sq in 0..64 {
+      let pos = term_pos(sq, flipped).unwrap();
+      assert_eq!(square_at(pos, flipped), Some(sq));
+      assert_eq!(square_at(pos - (2, 1), flipped), Some(sq));
+      assert_eq!(square_at(pos + (2, 1), flipped), Some(sq));
+    }
+  }
+  assert_eq!(term_pos(0, false), Some((3, 30).into()));
+  assert_eq!(term_pos(0, true), Some((45, 2).into()));
+  assert_eq!(square_at((6, 2).into(), false), None);
+  assert_eq!(square_at((3, 4).into(), false), None);
+  assert_eq!(square_at((50, 2).into(), true), None);
+}
+
+#[test]
+fn review_positions() {
+  let mut game = CheckersGame::new();
+  for mv in ["22-18", "11-15", "18x11"] {
+    game.play_move(&mv.parse().unwrap());
+  }
+  assert_eq!(game.position_at(0), (Checkerboard::new(), White));
+  let (board, color) = game.position_at(2);
+  assert_eq!((to_fen(&board, color).as_str(), color), ("W:W18,21,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,12,15", White));
+  assert_eq!(game.position_at(3), (*game.board(), Black));
+  assert_eq!(game.position_at(10), game.position_at(3));
+}
+
+#[test]
+fn theme_files() {
+  let theme = Theme::from_toml(indoc! { r##"
+      preset = "ascii"
+      [pieces]
+      white_king = "K"
+      [colors]
+      last_move = "#ffff00"
+      cursor = "light red"
+      hint = "green"
+  "##}).unwrap();
+  assert_eq!(theme.pieces.white_king, 'K');
+  assert_eq!(theme.pieces.black_man, 'b');
+  assert!(theme.ascii_lines);
+  assert_eq!(theme.colors.cursor, "light red".parse().unwrap());
+  assert_eq!(theme.colors.hint, "green".parse().unwrap());
+  assert_eq!(theme.colors.selected, Theme::default().colors.selected);
+  assert_eq!(Theme::from_toml("").unwrap(), Theme::default());
+  assert!(Theme::from_toml("preset = \"neon\"").is_err());
+  assert!(Theme::from_toml("[colors]\nforced = \"nope\"").is_err());
+  assert!(Theme::from_toml("[pieces]\nqueen = \"Q\"").is_err());
+}
+
+#[test]
+fn saved_game_round_trip() {
+  let mut game = CheckersGame::new();
+  game.set_clock("5+3".parse().unwrap());
+  game.play_move(&"22-18".parse().unwrap());
+  let mut pdn = game.to_pdn();
+  pdn.set_tag("WhiteClock", "290.5");
+  pdn.set_tag("BlackClock", "12.0");
+  let restored = CheckersGame::from_pdn(&parse_pdn(&pdn.to_string()).unwrap()[0]).unwrap();
+  assert_eq!(restored.board(), game.board());
+  assert_eq!(restored.on_move(), Black);
+  let clock = restored.clock().unwrap();
+  assert_eq!(clock.control().to_string(), "5+3");
+  assert_eq!(clock.remaining(White).as_millis(), 290_500);
+  assert_eq!(clock.running(), Some(Black));
+
+  pdn.result = "0-1".to_string();
+  pdn.set_tag("BlackClock", "soon");
+  assert!(CheckersGame::from_pdn(&pdn).is_err());
+  pdn.set_tag("BlackClock", "12.0");
+  assert_eq!(CheckersGame::from_pdn(&pdn).unwrap().result(), Some(GameResult::Victory(Black)));
+}
+
+#[test]
+fn position_setup() {
+  let square = |n: u8| number_square(n).unwrap();
+  let mut setup = Setup::new(&Checkerboard::new(), White);
+  setup.clear();
+  assert_eq!(setup.finish(), Err(PositionError::NoPieces(White)));
+  setup.place(square(18), Some((White, false))).unwrap();
+  setup.place(square(14), Some((Black, true))).unwrap();
+  assert_eq!(setup.place(square(2), Some((White, false))), Err(PositionError::ManOnPromotionRow(square(2))));
+  assert!(matches!(setup.place(Bitboard::from(2), Some((Black, false))), Err(PositionError::LightSquare(_))));
+  setup.place(square(2), Some((White, true))).unwrap();
+  setup.place(square(2), None).unwrap();
+  setup.on_move = Black;
+  let (board, color) = setup.finish().unwrap();
+  assert_eq!(to_fen(&board, color), "B:W18:BK14");
+
+  let mut full = Setup::new(&Checkerboard::new(), White);
+  assert_eq!(full.place(square(15), Some((Black, false))), Err(PositionError::TooManyPieces(Black)));
+  assert_eq!(full.board, Checkerboard::new());
+}
+
+#[test]
+fn search_lines_rank_candidates() {
+  let board = Checkerboard::new();
+  let single = Searcher::new(SearchLimits::depth(4)).search(&board, White);
+  let mut iterations = 0;
+  let lines = Searcher::new(SearchLimits::depth(4)).search_lines(&board, White, 3, |_| iterations += 1);
+  assert_eq!(iterations, 4);
+  assert_eq!(lines.len(), 3);
+  assert_eq!(lines[0].score, single.score);
+  assert!(lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+  assert!(lines.iter().all(|line| line.depth == 4 && line.pv.len() == 4));
+
+  // asking for more lines than there are moves gives one per legal move
+  let all = Searcher::new(SearchLimits::depth(2)).search_lines(&board, White, 20, |_| {});
+  assert_eq!(all.len(), legal_moves(&board, White).len());
+  let (stuck, color) = parse_fen("W:W:B1").unwrap();
+  assert!(Searcher::new(SearchLimits::depth(2)).search_lines(&stuck, color, 3, |_| {}).is_empty());
+}
+
+#[test]
+fn mcts_player() {
+  let limits = MctsLimits { iterations: Some(3000), time: None };
+  let (board, color) = parse_fen("B:W18,19,23,24,28,30,31,32:B1,2,3,4,6,10,11,12,21").unwrap();
+  for playout in [Playout::Random, Playout::Heuristic] {
+    let mut mcts = Mcts::new(limits.clone(), playout).with_seed(1);
+    let info = mcts.search(&board, color);
+    assert_eq!(info.best_move(), Some(&"10-15".parse().unwrap()));
+    assert!(info.score > 0);
+    assert_eq!(info.nodes, 3000);
+
+    // the subtree after the expected reply is kept
+    let mut next = board;
+    next.apply(color, &info.pv[0]);
+    next.apply(!color, &info.pv[1]);
+    mcts.set_limits(MctsLimits { iterations: Some(0), time: None });
+    let reused = mcts.search(&next, color);
+    assert!(mcts.tree_size() > 1);
+    assert_eq!(reused.nodes, 0);
+    assert_eq!(reused.best_move(), info.pv.get(2));
+  }
+
+  let (stuck, color) = parse_fen("W:W:B1").unwrap();
+  let info = Mcts::new(limits, Playout::Random).search(&stuck, color);
+  assert_eq!((info.best_move(), info.score), (None, -MATE));
+}
+
+#[test]
+fn game_review_marks_blunders() {
+  // 30-26 wins by force; 27-23 lets the game go on
+  let (board, color) = parse_fen("W:W27,30:B18,20").unwrap();
+  let moves: Vec<Move> = vec!["27-23".parse().unwrap(), "18-22".parse().unwrap()];
+  let review = review_game((board, color), &moves, &mut Searcher::new(SearchLimits::depth(8)));
+  assert_eq!(review.moves.len(), 2);
+  let first = &review.moves[0];
+  assert_eq!(first.best.to_string(), "30-26");
+  assert_eq!(first.quality(), MoveQuality::Blunder);
+  assert!(review.accuracy(White).unwrap() < 50.0);
+  assert!(review.summary(White).ends_with("0 inaccuracies, 0 mistakes, 1 blunder"));
+
+  let mut game = CheckersGame::from_position(board, color);
+  for mv in &moves {
+    game.play_move(mv);
+  }
+  let mut pdn = game.to_pdn();
+  review.annotate(&mut pdn);
+  let text = pdn.to_string();
+  assert!(text.contains("27-23 $4 {Blunder"));
+  assert!(text.contains("[WhiteAccuracy "));
+  assert_eq!(parse_pdn(&text).unwrap()[0].moves, pdn.moves);
+  assert_eq!(MoveQuality::from_loss(50), MoveQuality::Inaccuracy);
+  assert_eq!(MoveQuality::from_loss(10), MoveQuality::Good);
+}
+
+#[test]
+fn puzzle_solving() {
+  let puzzles = parse_puzzles(indoc! { r#"
+      [[puzzle]]
+      fen = "W:W27,30:B18,20"
+      solution = "30-26 20-24 27x20 18-22 26x17"
+      theme = "breakthrough"
+      rating = 1300
+
+      [[puzzle]]
+      fen = "W:WK10,K11:B1"
+      solution = "10-14 1-5 11-15 5-9 14x5"
+      theme = "endgame"
+  "#}).unwrap();
+  assert_eq!(puzzles.len(), 2);
+  assert_eq!(puzzles[1].rating, 1200);
+  assert_eq!(parse_puzzles(&write_puzzles(&puzzles).unwrap()).unwrap(), puzzles);
+  assert!(parse_puzzles("[[puzzle]]\nfen = \"W:W27,30:B18,20\"\nsolution = \"30-26 20-24\"\ntheme = \"x\"").is_err());
+  assert!(parse_puzzles("[[puzzle]]\nfen = \"W:W27,30:B18,20\"\nsolution = \"30-21\"\ntheme = \"x\"").is_err());
+
+  let puzzle = &puzzles[0];
+  let mut board = puzzle.board;
+  // depth-limited only, so the checks do not depend on the machine's speed
+  let mut searcher = Searcher::new(SearchLimits::depth(8));
+  let mut attempt = PuzzleAttempt::new(puzzle.clone());
+  for (played, reply) in [("30-26", "20-24"), ("27x20", "18-22")] {
+    let played: Move = played.parse().unwrap();
+    assert_eq!(attempt.check(&board, &played, &mut searcher), PuzzleStep::Correct { reply: reply.parse().unwrap() });
+    board.apply(White, &played);
+    board.apply(Black, &reply.parse().unwrap());
+  }
+  assert_eq!(attempt.check(&board, &"26x17".parse().unwrap(), &mut searcher), PuzzleStep::Solved);
+  assert_eq!(attempt.outcome, Some(true));
+
+  let mut attempt = PuzzleAttempt::new(puzzle.clone());
+  assert_eq!(attempt.check(&puzzle.board, &"27-24".parse().unwrap(), &mut searcher), PuzzleStep::Wrong { solution: "30-26".parse().unwrap() });
+  // another way to win counts
+  let mut attempt = PuzzleAttempt::new(puzzles[1].clone());
+  assert_eq!(attempt.check(&puzzles[1].board, &"11-15".parse().unwrap(), &mut searcher), PuzzleStep::Solved);
+
+  let mut stats = PuzzleStats::default();
+  assert_eq!(stats.next_puzzle(&puzzles), Some(&puzzles[1]));
+  stats.record(&puzzles[1], true);
+  assert_eq!((stats.rating, stats.streak, stats.best_streak), (1216, 1, 1));
+  assert_eq!(stats.next_puzzle(&puzzles), Some(&puzzles[0]));
+  stats.record(&puzzles[0], false);
+  assert_eq!((stats.rating, stats.streak, stats.best_streak, stats.failed), (1204, -1, 1, 1));
+  assert_eq!(stats.next_puzzle(&puzzles), Some(&puzzles[1]));
+  assert_eq!(toml::from_str::<PuzzleStats>(&toml::to_string(&stats).unwrap()).unwrap(), stats);
+}
+
+#[test]
+fn bundled_puzzles() {
+  // each starts with a clear win, ahead of every other move by the margin puzzles are extracted with
+  let puzzles = load_puzzles(None).unwrap();
+  assert!(puzzles.len() >= 40);
+  for puzzle in &puzzles {
+    let lines = Searcher::new(SearchLimits::depth(8)).search_lines(&puzzle.board, puzzle.color, 2, |_| {});
+    let id = puzzle.id();
+    assert_eq!(lines.len(), 2, "{}", id);
+    assert_eq!(lines[0].best_move(), Some(&puzzle.solution[0]), "{}", id);
+    assert!(lines[0].score >= SHOT_GAIN, "{} scores {}", id, lines[0].score);
+    assert!(lines[0].score - lines[1].score >= SHOT_GAIN - OTHER_GAIN, "{} is {} against {}", id, lines[0].score, lines[1].score);
+  }
+}
+
+#[test]
+fn puzzle_extraction() {
+  let start = parse_fen("B:W18,19,23,24,28,30,31,32:B1,2,3,4,6,10,11,12,21").unwrap();
+  let moves = |text: &str| -> Vec<Move> {
+    let (mut board, mut color) = start;
+    text.split_whitespace().map(|mv| {
+      let mv = resolve_move(&board, color, &mv.parse().unwrap()).unwrap();
+      board.apply(color, &mv);
+      color = !color;
+      mv
+    }).collect()
+  };
+  let mut searcher = Searcher::new(SearchLimits::depth(8));
+
+  let found = extract_puzzles(start, &moves("10-15 19x10 6x15x22"), &mut searcher);
+  assert_eq!(found.len(), 1);
+  assert_eq!((found[0].ply, found[0].found), (0, true));
+  assert_eq!(found[0].puzzle.solution, moves("10-15 19x10 6x15x22"));
+  assert_eq!(found[0].puzzle.theme, "shot");
+
+  let found = extract_puzzles(start, &moves("1-5"), &mut searcher);
+  assert_eq!(found.len(), 1);
+  assert!(!found[0].found);
+}
+
+#[test]
+fn network_evaluation() {
+  let network = Network::random(3);
+  let bytes = network.to_bytes();
+  assert_eq!(Network::from_bytes(&bytes).unwrap(), network);
+  assert!(Network::from_bytes(&bytes[..bytes.len() - 4]).is_err());
+  assert!(Network::from_bytes(b"not a network").is_err());
+
+  // a capture and a crowning update the accumulator like a fresh one
+  let lines = [("B:W18,19,23,24,28,30,31,32:B1,2,3,4,6,10,11,12,21", "10-15 19x10 6x15x22"), ("W:W6:B25", "6-1 25-29 1-6")];
+  for (fen, moves) in lines {
+    let (mut board, mut color) = parse_fen(fen).unwrap();
+    let mut accumulator = Accumulator::new(&network, &board);
+    for mv in moves.split_whitespace() {
+      let mv = resolve_move(&board, color, &mv.parse().unwrap()).unwrap();
+      let before = board;
+      board.apply(color, &mv);
+      color = !color;
+      accumulator.update(&network, &before, &board);
+      let fresh = Accumulator::new(&network, &board);
+      assert!(accumulator.values.iter().zip(&fresh.values).all(|(a, b)| (a - b).abs() < 1e-4));
+    }
+  }
+  let board = parse_fen("W:W18,19,24:B10,K27").unwrap().0;
+  assert_eq!(network.evaluate(&board, White), -network.evaluate(&board, Black));
+
+  let board = Checkerboard::new();
+  let info = Searcher::new(SearchLimits::depth(4)).with_network(Some(Arc::new(network.clone()))).search(&board, White);
+  assert!(legal_moves(&board, White).contains(info.best_move().unwrap()));
+}
+
+#[test]
+fn network_training() {
+  let mut rng = StdRng::seed_from_u64(5);
+  let options = SelfPlayOptions { depth: 2, random_plies: 4, max_plies: 80, ..SelfPlayOptions::default() };
+  let samples = play_training_game(&options, &[], &mut rng);
+  assert!(!samples.is_empty());
+  assert!(samples.iter().all(|sample| sample.outcome == samples[0].outcome));
+
+  let mut network = Network::random(5);
+  let mut losses = Vec::new();
+  let options = TrainingOptions { epochs: 5, ..TrainingOptions::default() };
+  train(&mut network, &samples, &options, &mut rng, |_, loss| losses.push(loss));
+  assert_eq!(losses.len(), 5);
+  assert!(losses[4] < losses[0]);
+}
+
+#[test]
+fn self_play_data() {
+  assert_eq!(openings(2).len(), 49);
+  let three_moves = openings(3);
+  assert!(three_moves.iter().all(|opening| opening.len() == 3));
+  assert!(three_moves.iter().enumerate().all(|(i, opening)| !three_moves[..i].contains(opening)));
+  // published openings have Black moving first and are turned round; the repeated one is kept once
+  let ballot = parse_pdn("1. 9-13 21-17 2. 5-9 *\n\n1. 11-15 23-19 2. 8-11 *\n\n1. 9-13 21-17 2. 5-9 *").unwrap();
+  let ballot = opening_lines(&ballot).unwrap();
+  assert_eq!(ballot.len(), 2);
+  assert!(ballot.iter().enumerate().all(|(i, opening)| !ballot[..i].contains(opening)));
+  let first: Vec<String> = ballot[0].iter().map(Move::to_string).collect();
+  assert_eq!(first, ["24-20", "12-16", "28-24"]);
+  assert!(ballot.iter().all(|opening| three_moves.contains(opening)));
+  assert!(opening_lines(&parse_pdn("[FEN \"W:W22:B11\"]\n1. 22-18 *").unwrap()).is_err());
+
+  let mut rng = StdRng::seed_from_u64(2);
+  let options = SelfPlayOptions { depth: 2, max_plies: 60, temperature: 100.0, temperature_plies: 20, ..SelfPlayOptions::default() };
+  let samples = play_training_game(&options, &three_moves[10], &mut rng);
+  assert!(!samples.is_empty());
+  let mut bytes = DataFormat::Binary.header();
+  for sample in &samples {
+    bytes.extend(sample.to_bytes(DataFormat::Binary));
+  }
+  assert_eq!(read_samples(&bytes).unwrap(), samples);
+  assert!(read_samples(&bytes[..bytes.len() - 1]).is_err());
+  assert!(read_samples(b"{\"fen\":").is_err());
+  // squares are written from White's side of the board, 29 first and 4 last
+  let (board, color) = parse_fen("W:W29,K1:B4,K32").unwrap();
+  let bytes = Sample { board, color, score: 0, outcome: 0.5 }.to_bytes(DataFormat::Binary);
+  let sets: Vec<u32> = bytes[..16].chunks(4).map(|set| u32::from_le_bytes(set.try_into().unwrap())).collect();
+  assert_eq!(sets, [1, 1 << 31, 1 << 28, 1 << 3]);
+
+  let sample = Sample { board: Checkerboard::new(), color: Black, score: -35, outcome: 0.5 };
+  let line = format!("{{\"fen\":\"{}\",\"turn\":\"black\",\"score\":-35,\"result\":0.5}}\n", to_fen(&sample.board, Black));
+  assert_eq!(String::from_utf8(sample.to_bytes(DataFormat::Jsonl)).unwrap(), line);
+
+  // 10-15 wins a man by force, so a low temperature always plays it and a high one plays anything
+  let (board, color) = parse_fen("B:W18,19,23,24,28,30,31,32:B1,2,3,4,6,10,11,12,21").unwrap();
+  let lines = Searcher::new(SearchLimits::depth(6)).search_lines(&board, color, 10, |_| {});
+  let best = resolve_move(&board, color, &"10-15".parse().unwrap()).unwrap();
+  assert!((0..20).all(|_| choose_by_temperature(&lines, 1.0, &mut rng) == Some(&best)));
+  assert!((0..50).any(|_| choose_by_temperature(&lines, 10_000.0, &mut rng) != Some(&best)));
+  assert_eq!(choose_by_temperature(&[], 1.0, &mut rng), None);
+}
+
+#[test]
+fn difficulty_levels() {
+  // the triple jump wins about a man and a half more than the double
+  let (board, color) = parse_fen("W:W22,30:B18,10,26,12").unwrap();
+  let triple = resolve_move(&board, color, &"30x23x14x7".parse().unwrap()).unwrap();
+  let mut rng = StdRng::seed_from_u64(1);
+  let mut triples = |difficulty: Difficulty| {
+    let mut searcher = Searcher::new(SearchLimits::depth(difficulty.depth()));
+    (0..100).filter(|_| difficulty.choose_move(&board, color, &mut searcher, &mut rng).best_move() == Some(&triple)).count()
+  };
+  let (beginner, advanced) = (triples(Difficulty::Beginner), triples(Difficulty::Advanced));
+  assert!(advanced >= 80);
+  assert!(beginner < advanced && beginner > 30);
+
+  // a lone legal move is played even if it is a multi-jump
+  let (board, color) = parse_fen("W:W22,25:B18,11,21,1").unwrap();
+  let info = Difficulty::Beginner.choose_move(&board, color, &mut Searcher::new(SearchLimits::depth(2)), &mut rng);
+  assert_eq!(info.best_move().map(Move::to_string), Some(String::from("22x15x8")));
+
+  // with no time left the search stops before any line is done, and a move is still played
+  let clock = ClockTime { remaining: Duration::from_millis(10), ..ClockTime::default() };
+  let limits = SearchLimits { depth: Some(Difficulty::Beginner.depth()), clock: Some(clock), ..SearchLimits::default() };
+  let mut searcher = Searcher::new(limits);
+  assert!(searcher.search_lines(&Checkerboard::new(), White, 7, |_| {}).is_empty());
+  let info = Difficulty::Beginner.choose_move(&Checkerboard::new(), White, &mut searcher, &mut rng);
+  assert!(info.best_move().is_some_and(|mv| legal_moves(&Checkerboard::new(), White).contains(mv)));
+}
+
+#[test]
+fn time_management() {
+  let clock = |remaining: u64, increment: u64, moves_to_go: Option<u32>| ClockTime {
+    remaining: Duration::from_millis(remaining),
+    increment: Duration::from_millis(increment),
+    moves_to_go,
+  };
+  let manager = TimeManager::new(&clock(60_000, 1000, None));
+  assert!(manager.optimum() > Duration::from_secs(2) && manager.optimum() < Duration::from_millis(2500));
+  assert!(manager.maximum() > manager.optimum() && manager.maximum() < Duration::from_secs(15));
+  // the last move of a period may take most of the time, but never all of it
+  let manager = TimeManager::new(&clock(10_000, 0, Some(1)));
+  assert!(manager.optimum() > Duration::from_secs(8) && manager.maximum() < Duration::from_secs(10) - MOVE_OVERHEAD);
+  assert_eq!(TimeManager::new(&clock(30, 0, None)).maximum(), Duration::ZERO);
+
+  // a stable search stops at half the planned time, an unstable or failing one goes on
+  let board = Checkerboard::new();
+  let moves = legal_moves(&board, White);
+  let info = |mv: usize, score: i32| SearchInfo { pv: vec![moves[mv].clone()], score, ..SearchInfo::default() };
+  let mut manager = TimeManager::new(&clock(60_000, 0, None));
+  let half = manager.optimum() / 2;
+  assert!(!manager.iteration_done(&info(0, 0), half / 2));
+  assert!(manager.iteration_done(&info(0, 0), half));
+  let mut manager = TimeManager::new(&clock(60_000, 0, None));
+  assert!(!manager.iteration_done(&info(0, 0), half / 2));
+  assert!(!manager.iteration_done(&info(1, 0), half));
+  let mut manager = TimeManager::new(&clock(60_000, 0, None));
+  assert!(!manager.iteration_done(&info(0, 0), half / 2));
+  assert!(!manager.iteration_done(&info(0, 20), half / 2));
+  assert!(!manager.iteration_done(&info(0, -150), half));
+
+  // a forced move is played at once, and a short clock is never overrun
+  let (board, color) = parse_fen("W:W22,24:B18,3").unwrap();
+  let limits = SearchLimits { clock: Some(clock(60_000, 0, None)), ..SearchLimits::default() };
+  let info = Searcher::new(limits).search(&board, color);
+  assert_eq!((info.depth, info.best_move().map(Move::to_string)), (1, Some(String::from("22x15"))));
+  let limits = SearchLimits { clock: Some(clock(300, 0, None)), ..SearchLimits::default() };
+  let info = Searcher::new(limits).search(&Checkerboard::new(), White);
+  assert!(info.elapsed < Duration::from_millis(300) - MOVE_OVERHEAD);
+  assert!(info.best_move().is_some());
+}
+
+#[test]
+fn quiescence_search() {
+  // 10-15 gives a man for two, which a plain one-ply search cannot see past 19x10
+  let (board, color) = parse_fen("B:W18,19,23,24,28,30,31,32:B1,2,3,4,6,10,11,12,21").unwrap();
+  let deep = Searcher::new(SearchLimits::depth(8)).search(&board, color);
+  assert_eq!(deep.best_move().map(Move::to_string).as_deref(), Some("10-15"));
+  let shallow = Searcher::new(SearchLimits::depth(1)).search(&board, color);
+  assert_eq!(shallow.best_move(), deep.best_move());
+  assert!((shallow.score - deep.score).abs() < 50);
+  let horizon = Searcher::new(SearchLimits::depth(1)).with_quiescence(0).search(&board, color);
+  assert_ne!(horizon.best_move(), deep.best_move());
+  assert!(horizon.score < shallow.score - 100);
+
+  // stopped halfway through the exchange, the sacrifice only looks like a lost man
+  let cut = Searcher::new(SearchLimits::depth(1)).with_quiescence(1).search(&board, color);
+  assert_ne!(cut.best_move(), deep.best_move());
+
+  // with the capture to make, the side to move is judged after the recapture too
+  let mut after = board;
+  after.apply(color, &"10-15".parse().unwrap());
+  let info = Searcher::new(SearchLimits::depth(1)).search(&after, !color);
+  assert_eq!(info.best_move().map(Move::to_string).as_deref(), Some("19x10"));
+  assert!((info.score + shallow.score).abs() < 50);
+
+  // no capture is possible after the first move, so nothing changes there
+  let quiet = Searcher::new(SearchLimits::depth(1)).search(&Checkerboard::new(), White);
+  let plain = Searcher::new(SearchLimits::depth(1)).with_quiescence(0).search(&Checkerboard::new(), White);
+  assert_eq!((quiet.score, &quiet.pv), (plain.score, &plain.pv));
+}
+
+#[test]
+fn move_list() {
+  let picked = Rc::new(Cell::new(None));
+  let recorder = picked.clone();
+  let mut list = MoveListView::new().on_select(move |_, ply| recorder.set(Some(ply)));
+  let moves = ["22-18", "11-15", "18x11", "8x15", "26x17x10x1", "7x14x23x30"];
+  list.set_moves(moves.map(String::from).to_vec(), false, moves.len());
+  // long multi-jumps widen both columns so the moves stay apart
+  assert_eq!(list.required_size(Vec2::new(80, 40)).x, 5 + 2 * 12);
+
+  let mut siv = Cursive::new();
+  let mut pick = |list: &mut MoveListView, event: Event| match list.on_event(event) {
+    EventResult::Consumed(Some(callback)) => {
+      callback(&mut siv);
+      picked.get()
+    }
+    _ => None,
+  };
+  // clicking Black's move in the second row shows the position after it
+  let click = |x: usize, y: usize| Event::Mouse { offset: Vec2::zero(), position: Vec2::new(x, y), event: MouseEvent::Press(MouseButton::Left) };
+  assert_eq!(pick(&mut list, click(5 + 12, 1)), Some(4));
+  assert_eq!(pick(&mut list, Event::Key(Key::Up)), Some(3));
+  assert_eq!(pick(&mut list, Event::Key(Key::End)), Some(6));
+  assert_eq!(pick(&mut list, click(5, 5)), None);
+  assert!(matches!(list.on_event(Event::Key(Key::Left)), EventResult::Ignored));
+}